@@ -0,0 +1,94 @@
+use std::path::Path;
+
+/// The longest extension kept within a key, as per git-annex (`annex.maxextensionlength`).
+const MAX_EXTENSION_LENGTH: usize = 4;
+
+/// Represents a git-annex key of the MD5 (or MD5E) backend, such as `MD5E-s1024--d41d8cd98f00b204e9800998ecf8427e.jpg`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Key {
+	/// The (hexadecimal MD5) checksum of the content.
+	pub checksum: String,
+
+	/// The size of the content in bytes, should it be recorded.
+	pub size: Option<u64>,
+}
+
+/// Returns the name of the MD5E key of content with the specified checksum, size and extension, which git-annex would give it.
+pub fn key(checksum: &str, size: u64, extension: Option<&str>) -> String {
+	// Extensions are only kept when short and plain, so that keys remain portable.
+
+	match extension.filter(|e| e.len() <= MAX_EXTENSION_LENGTH && e.chars().all(|c| c.is_ascii_alphanumeric())) {
+		Some(extension) => format!("MD5E-s{}--{}.{}", size, checksum, extension),
+		None => format!("MD5E-s{}--{}", size, checksum),
+	}
+}
+
+/// Parses the name of the specified file as a key of the MD5 or MD5E backend, should it be one.
+pub fn parse<T>(file: T) -> Option<Key>
+where
+	T: AsRef<Path>,
+{
+	let name = file.as_ref().file_name()?.to_str()?;
+
+	// Fields (such as the size) follow the backend and precede the checksum, which is followed by the extension for MD5E keys only.
+
+	let (head, checksum) = name.split_once("--")?;
+	let mut fields = head.split('-');
+	let backend = fields.next()?;
+
+	if backend != "MD5" && backend != "MD5E" {
+		return None;
+	}
+
+	let checksum = match backend {
+		"MD5E" => checksum.split_once('.').map(|(c, _)| c).unwrap_or(checksum),
+		_ => checksum,
+	};
+
+	if checksum.len() != 32 || !checksum.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+		return None;
+	}
+
+	let size = fields.find_map(|f| f.strip_prefix('s')).and_then(|s| s.parse().ok());
+
+	Some(Key {
+		checksum: checksum.to_owned(),
+		size,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The MD5 checksum of empty content.
+	const EMPTY: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+	#[test]
+	fn names_keys_as_git_annex_would() {
+		assert_eq!(key(EMPTY, 1024, Some("jpg")), format!("MD5E-s1024--{}.jpg", EMPTY));
+		assert_eq!(key(EMPTY, 0, None), format!("MD5E-s0--{}", EMPTY));
+		assert_eq!(key(EMPTY, 0, Some("jpeg2")), format!("MD5E-s0--{}", EMPTY));
+		assert_eq!(key(EMPTY, 0, Some("t.gz")), format!("MD5E-s0--{}", EMPTY));
+	}
+
+	#[test]
+	fn parses_md5_keys() {
+		let parsed = |name: String| parse(Path::new("annex").join(name));
+		let key = |size| Some(Key { checksum: EMPTY.to_owned(), size });
+
+		assert_eq!(parsed(format!("MD5E-s1024--{}.jpg", EMPTY)), key(Some(1024)));
+		assert_eq!(parsed(format!("MD5E-s1024--{}", EMPTY)), key(Some(1024)));
+		assert_eq!(parsed(format!("MD5-s0--{}", EMPTY)), key(Some(0)));
+		assert_eq!(parsed(format!("MD5--{}", EMPTY)), key(None));
+		assert_eq!(parsed(format!("MD5E-s1024-S512-C2--{}.jpg", EMPTY)), key(Some(1024)));
+
+		// Only MD5E keys carry an extension, and checksums are always lowercase.
+
+		assert_eq!(parsed(format!("MD5-s0--{}.jpg", EMPTY)), None);
+		assert_eq!(parsed(format!("MD5E-s0--{}", EMPTY.to_uppercase())), None);
+		assert_eq!(parsed(format!("MD5E-s0--{}", &EMPTY[1..])), None);
+		assert_eq!(parsed(format!("SHA256E-s0--{}.jpg", EMPTY)), None);
+		assert_eq!(parsed(format!("{}.jpg", EMPTY)), None);
+	}
+}
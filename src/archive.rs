@@ -0,0 +1,261 @@
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::{self, Read},
+	path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{output::warning, OrganiseError};
+
+/// Represents a particular file contained within an archive.
+#[derive(Debug, Clone)]
+pub struct Member {
+	/// The name (path) of the member within the archive.
+	pub name: String,
+
+	/// The checksum of the contents of the member.
+	pub digest: md5::Digest,
+
+	/// The size of the contents of the member, in bytes.
+	pub size: u64,
+}
+
+/// Represents a loose file whose contents also exist within an archive.
+#[derive(Debug, Clone)]
+pub struct ArchivedFile {
+	/// The path of the loose file.
+	pub path: PathBuf,
+
+	/// The path of the archive containing the same contents.
+	pub archive: PathBuf,
+
+	/// The name of the member within the archive containing the same contents.
+	pub member: String,
+
+	/// The size of the contents, in bytes.
+	pub size: u64,
+}
+
+/// Represents the names and contents of the regular files contained within an archive.
+pub type Contents = Vec<(String, Vec<u8>)>;
+
+/// Determines the format of a particular archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+	/// Indicates a zip archive.
+	Zip,
+
+	/// Indicates an uncompressed tar archive.
+	Tar,
+
+	/// Indicates a gzip-compressed tar archive.
+	TarGz,
+}
+
+/// The offset of the magic within the header of a tar archive.
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Determines the format of the specified file from its leading bytes, if it appears to be an archive.
+fn format<T>(file: T) -> io::Result<Option<Format>>
+where
+	T: AsRef<Path>,
+{
+	let mut header = Vec::with_capacity(512);
+
+	File::open(&file)?.take(512).read_to_end(&mut header)?;
+
+	let is_tar = |header: &[u8]| header.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5) == Some(b"ustar");
+
+	if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+		return Ok(Some(Format::Zip));
+	}
+
+	if is_tar(&header) {
+		return Ok(Some(Format::Tar));
+	}
+
+	if header.starts_with(&[0x1f, 0x8b]) {
+		let mut inner = Vec::with_capacity(512);
+
+		// Only treat compressed files as archives where the decompressed contents look like a tar archive.
+
+		if GzDecoder::new(File::open(&file)?).take(512).read_to_end(&mut inner).is_ok() && is_tar(&inner) {
+			return Ok(Some(Format::TarGz));
+		}
+	}
+
+	Ok(None)
+}
+
+/// Lists the regular files contained within the specified archive, or nothing if the file does not appear to be an archive.
+pub fn members<T>(file: T) -> Result<Option<Vec<Member>>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let Some(format) = format(&file).map_err(OrganiseError::FailedToReadFile)? else {
+		return Ok(None);
+	};
+
+	let handle = File::open(&file).map_err(OrganiseError::FailedToReadFile)?;
+
+	let members = match format {
+		Format::Zip => zip_members(handle),
+		Format::Tar => tar_members(handle),
+		Format::TarGz => tar_members(GzDecoder::new(handle)),
+	};
+
+	members.map(Some).map_err(OrganiseError::FailedToReadArchive)
+}
+
+/// Reads the names and contents of the regular files contained within the specified zip archive, or nothing if the file does not appear to be a zip archive.
+pub fn zip_contents<T>(file: T) -> Result<Option<Contents>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	if format(&file).map_err(OrganiseError::FailedToReadFile)? != Some(Format::Zip) {
+		return Ok(None);
+	}
+
+	let handle = File::open(&file).map_err(OrganiseError::FailedToReadFile)?;
+
+	let read = || -> io::Result<Contents> {
+		let mut archive = zip::ZipArchive::new(handle).map_err(io::Error::other)?;
+		let mut contents = Vec::with_capacity(archive.len());
+
+		for i in 0..archive.len() {
+			let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+
+			if !entry.is_file() {
+				continue;
+			}
+
+			let name = String::from_utf8_lossy(entry.name_raw()).into_owned();
+			let mut data = Vec::with_capacity(entry.size() as usize);
+
+			entry.read_to_end(&mut data)?;
+			contents.push((name, data));
+		}
+
+		Ok(contents)
+	};
+
+	read().map(Some).map_err(OrganiseError::FailedToReadArchive)
+}
+
+/// Lists the regular files contained within the specified zip archive.
+fn zip_members(handle: File) -> io::Result<Vec<Member>> {
+	let mut archive = zip::ZipArchive::new(handle).map_err(io::Error::other)?;
+	let mut members = Vec::with_capacity(archive.len());
+
+	for i in 0..archive.len() {
+		let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+
+		if !entry.is_file() {
+			continue;
+		}
+
+		let name = String::from_utf8_lossy(entry.name_raw()).into_owned();
+		let (digest, size) = digest(&mut entry)?;
+
+		members.push(Member { name, digest, size });
+	}
+
+	Ok(members)
+}
+
+/// Lists the regular files contained within the specified tar archive.
+fn tar_members<R>(reader: R) -> io::Result<Vec<Member>>
+where
+	R: Read,
+{
+	let mut archive = tar::Archive::new(reader);
+	let mut members = Vec::new();
+
+	for entry in archive.entries()? {
+		let mut entry = entry?;
+
+		if !entry.header().entry_type().is_file() {
+			continue;
+		}
+
+		let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+		let (digest, size) = digest(&mut entry)?;
+
+		members.push(Member { name, digest, size });
+	}
+
+	Ok(members)
+}
+
+/// Finds the loose files amongst the specified files whose contents also exist within one of the archives amongst the specified files.
+pub fn find_archived(files: &[PathBuf]) -> Vec<ArchivedFile> {
+	enum Indexed {
+		Archive(PathBuf, Vec<Member>),
+		Loose(PathBuf, md5::Digest, u64),
+	}
+
+	let indexed: Vec<Indexed> = files
+		.par_iter()
+		.filter_map(|f| {
+			let result = members(f).and_then(|m| match m {
+				Some(members) => Ok(Indexed::Archive(f.to_owned(), members)),
+				None => {
+					let (digest, size) = File::open(f).and_then(|mut h| digest(&mut h)).map_err(OrganiseError::FailedToReadFile)?;
+
+					Ok(Indexed::Loose(f.to_owned(), digest, size))
+				}
+			});
+
+			result.map_err(|e| warning!("Failed to index file <{}>: {}.", f.display(), e)).ok()
+		})
+		.collect();
+
+	let mut index: HashMap<md5::Digest, (&Path, &str)> = HashMap::new();
+
+	for item in &indexed {
+		if let Indexed::Archive(archive, members) = item {
+			for member in members.iter().filter(|m| m.size > 0) {
+				index.entry(member.digest).or_insert((archive, &member.name));
+			}
+		}
+	}
+
+	indexed
+		.iter()
+		.filter_map(|item| match item {
+			Indexed::Loose(path, digest, size) if *size > 0 => index.get(digest).map(|(archive, member)| ArchivedFile {
+				path: path.to_owned(),
+				archive: archive.to_path_buf(),
+				member: member.to_string(),
+				size: *size,
+			}),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Computes the checksum and size of the contents of the specified reader.
+fn digest<R>(reader: &mut R) -> io::Result<(md5::Digest, u64)>
+where
+	R: Read,
+{
+	let mut context = md5::Context::new();
+	let mut buffer = [0u8; 64 * 1024];
+	let mut size = 0u64;
+
+	loop {
+		let read = reader.read(&mut buffer)?;
+
+		if read == 0 {
+			break;
+		}
+
+		context.consume(&buffer[..read]);
+		size += read as u64;
+	}
+
+	Ok((context.compute(), size))
+}
@@ -0,0 +1,246 @@
+use std::path::Path;
+
+#[cfg(unix)]
+use std::{ffi::OsStr, io};
+
+#[cfg(unix)]
+use crate::output::{say, warning};
+
+/// The extended attributes holding Finder tags and colour labels, and Spotlight comments.
+#[cfg(target_os = "macos")]
+const PRESERVED_ATTRIBUTES: &[&str] = &["com.apple.metadata:_kMDItemUserTags", "com.apple.FinderInfo", "com.apple.metadata:kMDItemFinderComment"];
+
+/// The extended attributes recording where a file was downloaded from (and by which application), upon which Gatekeeper decides whether or not to check it when first opened.
+#[cfg(target_os = "macos")]
+const QUARANTINE_ATTRIBUTES: &[&str] = &["com.apple.quarantine", "com.apple.provenance"];
+
+/// The namespace of extended attributes set by users and applications, which (unlike other namespaces) can be carried over without privileges.
+#[cfg(all(unix, not(target_os = "macos")))]
+const USER_NAMESPACE: &str = "user.";
+
+/// The extended attribute holding the POSIX access control list of a file.
+#[cfg(all(unix, not(target_os = "macos")))]
+const ACL_ATTRIBUTE: &str = "system.posix_acl_access";
+
+/// The extended attribute holding the SELinux security context of a file.
+#[cfg(all(target_os = "linux", feature = "selinux"))]
+const SELINUX_ATTRIBUTE: &str = "security.selinux";
+
+/// Determines how the SELinux security contexts of files are handled when they are moved or copied elsewhere.
+#[cfg(all(target_os = "linux", feature = "selinux"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Labelling {
+	/// Indicates that files should keep their original security context.
+	Preserve,
+
+	/// Indicates that files should be relabelled with the default security context of their destination, according to policy (requires `restorecon`).
+	Relabel,
+}
+
+/// Preserves the extended attributes of the specified file (such as Finder tags and quarantine) on the specified file, which is a duplicate of it which survives in its place.
+///
+/// Attributes which already exist on the destination are left as they are, and failures are reported rather than treated as errors.
+pub fn preserve<T, U>(from: T, to: U)
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	// A quarantined file is never replaced by one which is not, so that content is still checked by Gatekeeper whichever copy survives.
+
+	#[cfg(target_os = "macos")]
+	for name in PRESERVED_ATTRIBUTES.iter().chain(QUARANTINE_ATTRIBUTES) {
+		if let Err(e) = copy(from.as_ref(), to.as_ref(), OsStr::new(name), false) {
+			warning!("Failed to preserve attribute <{}> of file <{}> on <{}>: {}.", name, from.as_ref().display(), to.as_ref().display(), e);
+		}
+	}
+
+	#[cfg(not(target_os = "macos"))]
+	let _ = (from, to);
+}
+
+/// Carries the extended attributes of the specified file (such as user attributes and access control lists) over to the specified copy of it.
+///
+/// Failures are reported rather than treated as errors, including where the file system of the copy cannot hold extended attributes at all.
+pub fn carry<T, U>(from: T, to: U)
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	#[cfg(unix)]
+	{
+		let (from, to) = (from.as_ref(), to.as_ref());
+
+		let names: Vec<_> = match xattr::list_deref(from) {
+			Ok(names) => names.filter(|n| is_carried(n)).collect(),
+			Err(e) if e.kind() == io::ErrorKind::Unsupported => return,
+			Err(e) => {
+				warning!("Failed to list attributes of file <{}>: {}.", from.display(), e);
+
+				return;
+			}
+		};
+
+		for name in names {
+			match copy(from, to, &name, true) {
+				Ok(()) => continue,
+				Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+					say!("Unable to preserve attributes of file <{}> as the file system of <{}> does not support them.", from.display(), to.display());
+
+					return;
+				}
+				Err(e) => warning!("Failed to preserve attribute <{}> of file <{}> on <{}>: {}.", name.to_string_lossy(), from.display(), to.display(), e),
+			}
+		}
+	}
+
+	#[cfg(not(unix))]
+	let _ = (from, to);
+}
+
+/// Returns whether or not the specified extended attribute is carried over to copies.
+#[cfg(unix)]
+fn is_carried(name: &OsStr) -> bool {
+	#[cfg(target_os = "macos")]
+	return PRESERVED_ATTRIBUTES.iter().chain(QUARANTINE_ATTRIBUTES).any(|a| OsStr::new(a) == name);
+
+	#[cfg(not(target_os = "macos"))]
+	return name.to_str().is_some_and(|n| n.starts_with(USER_NAMESPACE) || n == ACL_ATTRIBUTE);
+}
+
+/// Clears the quarantine of the specified file, so that Gatekeeper no longer checks it when first opened.
+///
+/// Failures are reported rather than treated as errors, as the content itself is unaffected.
+#[cfg(target_os = "macos")]
+pub fn clear_quarantine<T>(file: T)
+where
+	T: AsRef<Path>,
+{
+	// Provenance is recorded by the system itself, which only allows quarantine to be cleared.
+
+	let name = QUARANTINE_ATTRIBUTES[0];
+	let result = match xattr::get(&file, name) {
+		Ok(Some(_)) => xattr::remove(&file, name),
+		result => result.map(|_| ()),
+	};
+
+	if let Err(e) = result {
+		warning!("Failed to clear quarantine of file <{}>: {}.", file.as_ref().display(), e);
+	}
+}
+
+/// Copies the specified extended attribute from the specified file onto the specified file, optionally replacing it should it already exist there.
+#[cfg(unix)]
+fn copy(from: &Path, to: &Path, name: &OsStr, replace: bool) -> io::Result<()> {
+	let Some(value) = xattr::get_deref(from, name)? else {
+		return Ok(());
+	};
+
+	if !replace && xattr::get_deref(to, name)?.is_some() {
+		return Ok(());
+	}
+
+	xattr::set_deref(to, name, &value)
+}
+
+/// Reads the SELinux security context of the specified file, should it have one.
+#[cfg(all(target_os = "linux", feature = "selinux"))]
+pub fn context<T>(file: T) -> Option<Vec<u8>>
+where
+	T: AsRef<Path>,
+{
+	xattr::get(file, SELINUX_ATTRIBUTE).ok().flatten()
+}
+
+/// Labels the specified file, which has been moved or copied from elsewhere, with either its specified original security context or the default security context of its destination.
+///
+/// Failures are reported rather than treated as errors, as the content itself is unaffected.
+#[cfg(all(target_os = "linux", feature = "selinux"))]
+pub fn label<T>(file: T, labelling: Labelling, original: Option<&[u8]>)
+where
+	T: AsRef<Path>,
+{
+	let file = file.as_ref();
+
+	let result = match (labelling, original) {
+		(Labelling::Preserve, Some(context)) => xattr::set(file, SELINUX_ATTRIBUTE, context),
+		(Labelling::Preserve, None) => Ok(()),
+		(Labelling::Relabel, _) => std::process::Command::new("restorecon").arg(file).status().and_then(|s| match s.success() {
+			true => Ok(()),
+			false => Err(io::Error::other(format!("restorecon exited with {}", s))),
+		}),
+	};
+
+	if let Err(e) = result {
+		warning!("Failed to label file <{}>: {}.", file.display(), e);
+	}
+}
+
+#[cfg(all(test, target_os = "macos"))]
+mod tests {
+	use std::{fs, path::PathBuf};
+
+	use super::*;
+	use crate::testing::scratch;
+
+	/// The attributes holding the Finder tags and Spotlight comment of a file, along with values standing in for them (which are binary property lists, whose contents are never interpreted).
+	const TAGS: &[(&str, &[u8])] = &[("com.apple.metadata:_kMDItemUserTags", b"bplist00\xa1\x01URed\n6"), ("com.apple.metadata:kMDItemFinderComment", b"bplist00_\x10\x0fHoliday in Rome")];
+
+	/// Creates two files of the same contents within a directory of the specified name, tagging the first of them.
+	fn tagged(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+		let dir = scratch(name);
+		let (from, to) = (dir.join("a.txt"), dir.join("b.txt"));
+
+		fs::write(&from, "a").unwrap();
+		fs::write(&to, "a").unwrap();
+
+		for (name, value) in TAGS {
+			xattr::set(&from, name, value).unwrap();
+		}
+
+		(dir, from, to)
+	}
+
+	#[test]
+	fn preserves_tags_on_duplicates() {
+		let (dir, from, to) = tagged("preserve-tags");
+
+		preserve(&from, &to);
+
+		for (name, value) in TAGS {
+			assert_eq!(xattr::get(&to, name).unwrap().as_deref(), Some(*value));
+		}
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn preserves_existing_tags() {
+		let (dir, from, to) = tagged("preserve-existing-tags");
+
+		xattr::set(&to, TAGS[0].0, b"existing").unwrap();
+
+		preserve(&from, &to);
+
+		assert_eq!(xattr::get(&to, TAGS[0].0).unwrap().as_deref(), Some(&b"existing"[..]));
+		assert_eq!(xattr::get(&to, TAGS[1].0).unwrap().as_deref(), Some(TAGS[1].1));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn carries_tags_to_copies() {
+		let (dir, from, to) = tagged("carry-tags");
+
+		xattr::set(&from, "com.example.unrelated", b"a").unwrap();
+
+		carry(&from, &to);
+
+		for (name, value) in TAGS {
+			assert_eq!(xattr::get(&to, name).unwrap().as_deref(), Some(*value));
+		}
+
+		assert_eq!(xattr::get(&to, "com.example.unrelated").unwrap(), None);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,238 @@
+use std::{
+	collections::HashMap,
+	fs::{self, Metadata},
+	io,
+	path::{Path, PathBuf},
+	sync::Mutex,
+	time::UNIX_EPOCH,
+};
+
+use clap::ValueEnum;
+
+use crate::{hashing::Hash, output::warning};
+
+/// The name of the index within the directory which caches the checksums of the files beneath it.
+const INDEX_FILE: &str = ".organiser-index";
+
+/// Represents the checksum of a file as it was when last hashed, along with its size and last modified time then.
+#[derive(Debug, Clone)]
+struct Cached {
+	/// The size of the file, in bytes.
+	size: u64,
+
+	/// When the file was last modified, in nanoseconds since the Unix epoch.
+	modified: u128,
+
+	/// The checksum of the contents of the file.
+	checksum: String,
+}
+
+impl Cached {
+	/// Returns whether or not the specified metadata describes the file as it was when it was cached.
+	fn describes(&self, metadata: &Metadata) -> bool {
+		metadata.len() == self.size && modified(metadata) == Some(self.modified)
+	}
+}
+
+/// Represents the index of the checksums of the files beneath a directory, so that files which have not changed since they were last hashed need not be hashed again.
+#[derive(Debug)]
+pub struct Cache {
+	/// The directory which the index resides within, and which the files within it are relative to.
+	dir: PathBuf,
+
+	/// The algorithm the checksums within the index were computed by.
+	algorithm: Hash,
+
+	/// The checksums within the index as it was before this run, by the path of each file.
+	previous: HashMap<PathBuf, Cached>,
+
+	/// The checksums of the files hashed (or found unchanged) during this run, by the path of each file.
+	current: Mutex<HashMap<PathBuf, Cached>>,
+}
+
+impl Cache {
+	/// Opens the index within the specified directory of the checksums computed by the specified algorithm, which starts out empty should it be rebuilt (or not yet exist).
+	pub fn open<T>(dir: T, algorithm: Hash, rebuild: bool) -> Self
+	where
+		T: AsRef<Path>,
+	{
+		let index = dir.as_ref().join(INDEX_FILE);
+
+		let contents = match fs::read_to_string(&index) {
+			Ok(_) if rebuild => String::new(),
+			Ok(contents) => contents,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+			Err(e) => {
+				warning!("Failed to read cache <{}>, hashing every file: {}.", index.display(), e);
+
+				String::new()
+			}
+		};
+
+		// Checksums computed by another algorithm are of no use, so they are discarded and replaced as files are hashed again.
+
+		let name = name(algorithm);
+		let previous = contents
+			.lines()
+			.filter_map(|line| {
+				let [cached, size, modified, checksum, path] = line.splitn(5, '\t').collect::<Vec<_>>()[..] else {
+					return None;
+				};
+
+				(cached == name).then_some(())?;
+
+				Some((
+					PathBuf::from(path),
+					Cached {
+						size: size.parse().ok()?,
+						modified: modified.parse().ok()?,
+						checksum: checksum.to_owned(),
+					},
+				))
+			})
+			.collect();
+
+		Self {
+			dir: dir.as_ref().to_owned(),
+			algorithm,
+			previous,
+			current: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Returns the algorithm the checksums within the index are computed by.
+	pub fn algorithm(&self) -> Hash {
+		self.algorithm
+	}
+
+	/// Returns the path of the specified file relative to the directory.
+	fn relative(&self, file: &Path) -> PathBuf {
+		file.strip_prefix(&self.dir).map(Path::to_owned).unwrap_or_else(|_| file.to_owned())
+	}
+
+	/// Returns the cached checksum of the specified file, should it not have changed (as described by the specified metadata) since it was last hashed.
+	pub fn get<T>(&self, file: T, metadata: &Metadata) -> Option<String>
+	where
+		T: AsRef<Path>,
+	{
+		let cached = self.previous.get(&self.relative(file.as_ref())).filter(|c| c.describes(metadata))?;
+
+		Some(cached.checksum.clone())
+	}
+
+	/// Records the specified checksum of the specified file, as it is now.
+	pub fn record<T>(&self, file: T, checksum: &str)
+	where
+		T: AsRef<Path>,
+	{
+		let Some((size, modified)) = file.as_ref().metadata().ok().and_then(|m| Some((m.len(), modified(&m)?))) else {
+			return;
+		};
+
+		self.current.lock().unwrap().insert(self.relative(file.as_ref()), Cached { size, modified, checksum: checksum.to_owned() });
+	}
+
+	/// Writes the index, recording the checksums of the files hashed during this run alongside those cached before which still describe their files, returning the number of checksums written.
+	pub fn write(&self) -> io::Result<usize> {
+		let mut entries = self.current.lock().unwrap().clone();
+
+		// Files which were not visited during this run (such as those already organised, in fast mode) keep their checksums for as long as they remain unchanged, whereas those which were renamed or removed are forgotten.
+
+		for (path, cached) in &self.previous {
+			if !entries.contains_key(path) && self.dir.join(path).metadata().is_ok_and(|m| cached.describes(&m)) {
+				entries.insert(path.clone(), cached.clone());
+			}
+		}
+
+		let mut entries: Vec<_> = entries.into_iter().collect();
+
+		entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+		let name = name(self.algorithm);
+		let contents: String = entries.iter().map(|(path, c)| format!("{}\t{}\t{}\t{}\t{}\n", name, c.size, c.modified, c.checksum, path.display())).collect();
+
+		fs::write(self.dir.join(INDEX_FILE), contents)?;
+
+		Ok(entries.len())
+	}
+}
+
+/// Returns the name of the specified algorithm, as given upon the command line.
+fn name(algorithm: Hash) -> String {
+	algorithm.to_possible_value().map(|v| v.get_name().to_owned()).unwrap_or_default()
+}
+
+/// Returns when the file described by the specified metadata was last modified, in nanoseconds since the Unix epoch.
+fn modified(metadata: &Metadata) -> Option<u128> {
+	metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok().map(|d| d.as_nanos())
+}
+
+/// Returns whether or not the specified file is the index, which is never organised itself.
+pub fn is_index<T>(file: T) -> bool
+where
+	T: AsRef<Path>,
+{
+	file.as_ref().file_name().is_some_and(|n| n == INDEX_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::scratch;
+
+	#[test]
+	fn reuses_checksums_of_unchanged_files() {
+		let dir = scratch("cache-hits");
+		let (unchanged, changed, removed) = (dir.join("a.txt"), dir.join("b.txt"), dir.join("c.txt"));
+
+		for file in [&unchanged, &changed, &removed] {
+			fs::write(file, "one").unwrap();
+		}
+
+		let cache = Cache::open(&dir, Hash::Md5, false);
+
+		for file in [&unchanged, &changed, &removed] {
+			assert_eq!(cache.get(file, &file.metadata().unwrap()), None);
+
+			cache.record(file, "checksum");
+		}
+
+		assert_eq!(cache.write().unwrap(), 3);
+
+		fs::write(&changed, "changed").unwrap();
+		fs::remove_file(&removed).unwrap();
+
+		let cache = Cache::open(&dir, Hash::Md5, false);
+
+		assert_eq!(cache.get(&unchanged, &unchanged.metadata().unwrap()), Some("checksum".to_owned()));
+		assert_eq!(cache.get(&changed, &changed.metadata().unwrap()), None);
+
+		// Files not visited keep their checksums whilst unchanged, whereas those removed are forgotten.
+
+		assert_eq!(cache.write().unwrap(), 1);
+		assert_eq!(Cache::open(&dir, Hash::Md5, false).get(&unchanged, &unchanged.metadata().unwrap()), Some("checksum".to_owned()));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn ignores_other_algorithms_and_rebuilds() {
+		let dir = scratch("cache-misses");
+		let file = dir.join("a.txt");
+
+		fs::write(&file, "one").unwrap();
+
+		let cache = Cache::open(&dir, Hash::Md5, false);
+
+		cache.record(&file, "checksum");
+		cache.write().unwrap();
+
+		let metadata = file.metadata().unwrap();
+
+		assert_eq!(Cache::open(&dir, Hash::Sha256, false).get(&file, &metadata), None);
+		assert_eq!(Cache::open(&dir, Hash::Md5, true).get(&file, &metadata), None);
+		assert_eq!(Cache::open(&dir, Hash::Md5, false).get(&file, &metadata), Some("checksum".to_owned()));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
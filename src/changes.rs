@@ -0,0 +1,149 @@
+use std::{
+	fs,
+	io,
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+
+use clap::ValueEnum;
+
+/// Determines the form of a list of changes, which replays the changes made to a directory upon a mirror of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChangeFormat {
+	/// Indicates a shell script of `mv` and `rm` commands, run from within a local (or `rsync` destination) mirror.
+	Shell,
+
+	/// Indicates a shell script of `rclone moveto` and `rclone deletefile` commands, run against an rclone remote.
+	Rclone,
+}
+
+/// Determines a particular change made to a file within the directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+	/// Indicates that a file was renamed (or moved into a folder) where nothing existed before.
+	Rename(PathBuf, PathBuf),
+
+	/// Indicates that a file was renamed over an existing (duplicate) file.
+	Replace(PathBuf, PathBuf),
+
+	/// Indicates that a (duplicate) file was removed.
+	Remove(PathBuf),
+}
+
+/// Represents the changes made whilst organising a directory, recorded relative to it.
+#[derive(Debug)]
+pub struct ChangeList {
+	/// The directory which the changes are relative to.
+	dir: PathBuf,
+
+	/// The changes made, in the order they were made.
+	changes: Mutex<Vec<Change>>,
+}
+
+impl ChangeList {
+	/// Creates an empty list of the changes made to the specified directory.
+	pub fn new<T>(dir: T) -> Self
+	where
+		T: AsRef<Path>,
+	{
+		Self {
+			dir: dir.as_ref().to_owned(),
+			changes: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Records the specified change.
+	pub fn record(&self, change: Change) {
+		let relative = |path: PathBuf| path.strip_prefix(&self.dir).map(Path::to_owned).unwrap_or(path);
+
+		let change = match change {
+			Change::Rename(from, to) => Change::Rename(relative(from), relative(to)),
+			Change::Replace(from, to) => Change::Replace(relative(from), relative(to)),
+			Change::Remove(file) => Change::Remove(relative(file)),
+		};
+
+		self.changes.lock().unwrap().push(change);
+	}
+
+	/// Returns the number of changes recorded.
+	pub fn len(&self) -> usize {
+		self.changes.lock().unwrap().len()
+	}
+
+	/// Writes the recorded changes to the specified file in the specified form.
+	pub fn write<T>(&self, file: T, format: ChangeFormat) -> io::Result<()>
+	where
+		T: AsRef<Path>,
+	{
+		let mut changes = self.changes.lock().unwrap().clone();
+
+		// Files are organised in parallel, so the changes are replayed in phases which are correct regardless of the order they were made in: renames never overwrite, so only the first file with some content claims its name, before any replacements and removals.
+
+		changes.sort_by_key(|c| match c {
+			Change::Rename(..) => 0,
+			Change::Replace(..) => 1,
+			Change::Remove(..) => 2,
+		});
+
+		let mut folders: Vec<&Path> = changes
+			.iter()
+			.filter_map(|c| match c {
+				Change::Rename(_, to) | Change::Replace(_, to) => to.parent().filter(|p| !p.as_os_str().is_empty()),
+				Change::Remove(_) => None,
+			})
+			.collect();
+
+		folders.sort();
+		folders.dedup();
+
+		let mut script = String::from("#!/bin/sh\n");
+
+		match format {
+			ChangeFormat::Shell => {
+				script.push_str("# Replays the changes made by directory-organiser within the specified mirror.\n");
+				script.push_str("cd \"${1:?usage: $0 <mirror>}\" || exit 1\n\n");
+
+				for folder in folders {
+					script.push_str(&format!("mkdir -p -- {}\n", quote(folder)));
+				}
+
+				for change in &changes {
+					let line = match change {
+						Change::Rename(from, to) => format!("mv -n -- {} {}", quote(from), quote(to)),
+						Change::Replace(from, to) => format!("mv -f -- {} {}", quote(from), quote(to)),
+						Change::Remove(file) => format!("rm -f -- {}", quote(file)),
+					};
+
+					script.push_str(&line);
+					script.push('\n');
+				}
+			}
+			ChangeFormat::Rclone => {
+				script.push_str("# Replays the changes made by directory-organiser upon the specified rclone remote (such as remote:backup).\n");
+				script.push_str("mirror=\"${1:?usage: $0 <remote:path>}\"\n\n");
+
+				// Folders are implied by the files within them on most remotes, and are otherwise created by moving into them.
+
+				for change in &changes {
+					let line = match change {
+						Change::Rename(from, to) => format!("rclone moveto --ignore-existing \"$mirror\"/{} \"$mirror\"/{}", quote(from), quote(to)),
+						Change::Replace(from, to) => format!("rclone moveto \"$mirror\"/{} \"$mirror\"/{}", quote(from), quote(to)),
+						Change::Remove(file) => format!("rclone deletefile \"$mirror\"/{}", quote(file)),
+					};
+
+					script.push_str(&line);
+					script.push('\n');
+				}
+			}
+		}
+
+		fs::write(file, script)
+	}
+}
+
+/// Quotes the specified (relative) path for use as a single argument within a POSIX shell, using forward slashes as separators.
+fn quote(path: &Path) -> String {
+	let path = path.iter().map(|c| c.to_string_lossy()).collect::<Vec<_>>().join("/");
+
+	format!("'{}'", path.replace('\'', r"'\''"))
+}
@@ -0,0 +1,551 @@
+use std::{
+	collections::HashMap,
+	env,
+	fs::{self, File},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+	cache::{self, Cache},
+	hashing::{self, Hash, Hasher},
+	journal, links, paths, OrganiseError,
+};
+
+/// The header which begins hashdeep audit files, as of version 1.0 of the format.
+const HASHDEEP_HEADER: &str = "%%%% HASHDEEP-1.0";
+
+/// Determines the algorithm of a checksum file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+	/// Indicates an `MD5SUMS` file, as produced by `md5sum`.
+	Md5,
+
+	/// Indicates a `SHA256SUMS` file, as produced by `sha256sum`.
+	Sha256,
+
+	/// Indicates a simple file verification (SFV) file, which is only ever read.
+	#[value(skip)]
+	Crc32,
+
+	/// Indicates a hashdeep (or md5deep) audit file, listing the size, MD5 and SHA-256 checksums of each file.
+	Hashdeep,
+}
+
+/// Determines the outcome of verifying a particular file against a checksum file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+	/// Indicates that the contents of the file match its listed checksum.
+	Matched,
+
+	/// Indicates that the contents of the file do not match its listed checksum.
+	Mismatched,
+
+	/// Indicates that the file listed within the checksum file does not exist.
+	Missing,
+}
+
+/// Represents the verification of a particular file listed within a checksum file.
+#[derive(Debug, Clone)]
+pub struct Verification {
+	/// The path of the listed file.
+	pub file: PathBuf,
+
+	/// The path of the checksum file listing the file.
+	pub listing: PathBuf,
+
+	/// The outcome of verifying the file.
+	pub outcome: Outcome,
+}
+
+/// Represents a particular file listed within a hashdeep audit file, where any of its fields might be omitted.
+#[derive(Debug, Clone, Default)]
+struct Audited {
+	/// The path of the file, which is either absolute or relative to where the audit was made.
+	name: String,
+
+	/// The size of the file in bytes.
+	size: Option<String>,
+
+	/// The MD5 checksum of the file.
+	md5: Option<String>,
+
+	/// The SHA-256 checksum of the file.
+	sha256: Option<String>,
+}
+
+impl Algorithm {
+	/// Returns the conventional name of the checksum file for this algorithm.
+	pub fn file_name(&self) -> &'static str {
+		match self {
+			Self::Md5 => "MD5SUMS",
+			Self::Sha256 => "SHA256SUMS",
+			Self::Crc32 => "checksums.sfv",
+			Self::Hashdeep => "hashdeep.txt",
+		}
+	}
+
+	/// Determines the algorithm of the specified checksum file from its name, if it appears to be a checksum file.
+	pub fn of<T>(file: T) -> Option<Self>
+	where
+		T: AsRef<Path>,
+	{
+		let name = file.as_ref().file_name().and_then(|n| n.to_str()).unwrap_or_default();
+		let extension = file.as_ref().extension().and_then(|e| e.to_str()).unwrap_or_default();
+
+		match (name.to_ascii_lowercase().as_str(), extension.to_ascii_lowercase().as_str()) {
+			("md5sums", _) | (_, "md5") => Some(Self::Md5),
+			("sha256sums", _) | (_, "sha256") => Some(Self::Sha256),
+			(_, "sfv") => Some(Self::Crc32),
+			("hashdeep.txt", _) | (_, "hashdeep") => Some(Self::Hashdeep),
+			_ => None,
+		}
+	}
+
+	/// Returns the algorithm listing the same checksums as organised files are named by with the specified algorithm, or SHA-256 should no checksum file list those.
+	pub fn named(algorithm: Hash) -> Self {
+		match algorithm {
+			Hash::Md5 => Self::Md5,
+			Hash::Sha256 | Hash::Blake3 | Hash::Xxh3 => Self::Sha256,
+		}
+	}
+
+	/// Computes the checksum of the contents of the specified file using this algorithm, which for hashdeep audits is the size, MD5 and SHA-256 checksums of the file separated by commas.
+	pub fn compute<T>(&self, file: T) -> io::Result<String>
+	where
+		T: AsRef<Path>,
+	{
+		match self {
+			Self::Md5 => Hash::Md5.compute(file),
+			Self::Sha256 => Hash::Sha256.compute(file),
+			Self::Crc32 => {
+				let mut crc32 = crc32fast::Hasher::new();
+
+				hashing::read(file, |chunk| crc32.update(chunk))?;
+
+				Ok(format!("{:08x}", crc32.finalize()))
+			}
+
+			// Audits list both checksums of each file, which are computed together so that it is only read once.
+
+			Self::Hashdeep => {
+				let (mut md5, mut sha256) = (Hasher::new(Hash::Md5), Hasher::new(Hash::Sha256));
+
+				let size = hashing::read(file, |chunk| {
+					md5.update(chunk);
+					sha256.update(chunk);
+				})?;
+
+				Ok(format!("{},{},{}", size, md5.finish(), sha256.finish()))
+			}
+		}
+	}
+
+	/// Returns the tag identifying this algorithm within BSD-style checksum lines (such as `MD5 (name) = checksum`).
+	fn tag(&self) -> &'static str {
+		match self {
+			Self::Md5 => "MD5",
+			Self::Sha256 => "SHA256",
+			Self::Crc32 => "CRC32",
+			Self::Hashdeep => "HASHDEEP",
+		}
+	}
+
+	/// Returns the algorithm whose checksums organised files are named by which computes the same checksums as this one, should there be one.
+	fn hash(&self) -> Option<Hash> {
+		match self {
+			Self::Md5 => Some(Hash::Md5),
+			Self::Sha256 => Some(Hash::Sha256),
+			Self::Crc32 | Self::Hashdeep => None,
+		}
+	}
+
+	/// Returns the length of a checksum produced by this algorithm, in hexadecimal digits.
+	fn length(&self) -> usize {
+		match self {
+			Self::Md5 => 32,
+			Self::Sha256 => 64,
+			Self::Crc32 => 8,
+			Self::Hashdeep => 0,
+		}
+	}
+
+	/// Returns whether or not the specified computed checksum matches the specified listed checksum, where the fields omitted from hashdeep audits are ignored.
+	fn matches(&self, actual: &str, listed: &str) -> bool {
+		match self {
+			Self::Hashdeep => actual.split(',').zip(listed.split(',')).all(|(a, l)| l.is_empty() || a.eq_ignore_ascii_case(l)),
+			_ => actual.eq_ignore_ascii_case(listed),
+		}
+	}
+}
+
+/// Returns whether or not the specified file is a checksum file, which is never organised itself.
+pub fn is_checksum_file<T>(file: T) -> bool
+where
+	T: AsRef<Path>,
+{
+	Algorithm::of(file).is_some()
+}
+
+/// Verifies the files listed within each of the checksum files amongst the specified files.
+pub fn verify(files: &[PathBuf]) -> Result<Vec<Verification>, OrganiseError> {
+	let mut listed = Vec::new();
+
+	for listing in files {
+		if let Some(algorithm) = Algorithm::of(listing) {
+			let folder = listing.parent().unwrap_or(Path::new(""));
+			let contents = fs::read(listing).map_err(OrganiseError::FailedToReadFile)?;
+
+			for (name, checksum) in parse(&String::from_utf8_lossy(&contents), algorithm) {
+				listed.push((folder.join(name), checksum, listing, algorithm));
+			}
+		}
+	}
+
+	listed
+		.par_iter()
+		.map(|(file, checksum, listing, algorithm)| {
+			let outcome = match algorithm.compute(file) {
+				Ok(actual) if algorithm.matches(&actual, checksum) => Outcome::Matched,
+				Ok(_) => Outcome::Mismatched,
+				Err(e) if e.kind() == io::ErrorKind::NotFound => Outcome::Missing,
+				Err(e) => return Err(OrganiseError::FailedToReadFile(e)),
+			};
+
+			Ok(Verification {
+				file: file.to_owned(),
+				listing: listing.to_path_buf(),
+				outcome,
+			})
+		})
+		.collect()
+}
+
+/// Returns the MD5 checksums listed within the hashdeep audits amongst the specified files, by the canonical path of each file which has not changed (in size or last modified time) since it was audited.
+pub fn audited(files: &[PathBuf]) -> HashMap<PathBuf, String> {
+	let mut checksums = HashMap::new();
+
+	for listing in files.iter().filter(|f| Algorithm::of(f) == Some(Algorithm::Hashdeep)) {
+		let (Ok(contents), Ok(written)) = (fs::read(listing), listing.metadata().and_then(|m| m.modified())) else {
+			continue;
+		};
+
+		let folder = listing.parent().unwrap_or(Path::new(""));
+
+		for entry in audit(&String::from_utf8_lossy(&contents)) {
+			let (Some(size), Some(md5)) = (entry.size, entry.md5) else {
+				continue;
+			};
+
+			let Ok(metadata) = fs::metadata(folder.join(&entry.name)) else {
+				continue;
+			};
+
+			let unchanged = metadata.len().to_string() == size && metadata.modified().is_ok_and(|m| m <= written);
+
+			if let (true, Ok(path)) = (unchanged, fs::canonicalize(folder.join(&entry.name))) {
+				checksums.insert(path, md5.to_ascii_lowercase());
+			}
+		}
+	}
+
+	checksums
+}
+
+/// Parses the files listed within the specified contents of a hashdeep audit file, resolving relative names against where the audit was made.
+fn audit(contents: &str) -> Vec<Audited> {
+	let mut columns: Vec<String> = Vec::new();
+	let mut invoked = None;
+	let mut entries = Vec::new();
+
+	for line in contents.lines().map(|l| l.trim_end_matches('\r')) {
+		if line == HASHDEEP_HEADER {
+			continue;
+		}
+
+		// The columns are declared by the second header line, and relative names are relative to where hashdeep was invoked.
+
+		if let Some(header) = line.strip_prefix("%%%% ") {
+			columns = header.split(',').map(str::to_ascii_lowercase).collect();
+
+			continue;
+		}
+
+		if let Some(dir) = line.strip_prefix("## Invoked from: ") {
+			invoked = Some(PathBuf::from(dir));
+
+			continue;
+		}
+
+		if line.starts_with('#') || line.trim().is_empty() || columns.last().map(String::as_str) != Some("filename") {
+			continue;
+		}
+
+		let fields: Vec<&str> = line.splitn(columns.len(), ',').collect();
+
+		if fields.len() != columns.len() {
+			continue;
+		}
+
+		let mut entry = Audited::default();
+
+		for (column, field) in columns.iter().zip(&fields) {
+			match column.as_str() {
+				"size" => entry.size = Some(field.to_string()),
+				"md5" => entry.md5 = Some(field.to_string()),
+				"sha256" => entry.sha256 = Some(field.to_string()),
+				"filename" => entry.name = field.to_string(),
+				_ => continue,
+			}
+		}
+
+		if let Some(dir) = invoked.as_ref().filter(|_| Path::new(&entry.name).is_relative()) {
+			entry.name = dir.join(&entry.name).to_string_lossy().into_owned();
+		}
+
+		entries.push(entry);
+	}
+
+	entries
+}
+
+/// Parses the names and checksums of the files listed within the specified contents of a checksum file, ignoring comments and malformed lines.
+fn parse(contents: &str, algorithm: Algorithm) -> Vec<(String, String)> {
+	let mut entries = Vec::new();
+
+	// Checksums of hashdeep audits are formed of each of their fields (as computed for them), of which some may be omitted.
+
+	if algorithm == Algorithm::Hashdeep {
+		return audit(contents)
+			.into_iter()
+			.filter(|e| !e.name.is_empty() && (e.md5.is_some() || e.sha256.is_some()))
+			.map(|e| (e.name, format!("{},{},{}", e.size.unwrap_or_default(), e.md5.unwrap_or_default(), e.sha256.unwrap_or_default())))
+			.collect();
+	}
+
+	for line in contents.lines().map(|l| l.trim_end_matches('\r')) {
+		if line.trim().is_empty() || line.starts_with([';', '#']) {
+			continue;
+		}
+
+		let entry = match algorithm {
+			// Simple file verification lines list the name first, which may contain spaces, and often use Windows separators.
+			Algorithm::Crc32 => line.trim_end().rsplit_once([' ', '\t']).map(|(name, checksum)| (name.trim_end().replace('\\', "/"), checksum.to_owned())),
+
+			// Lines are either in the coreutils format (optionally escaped, and optionally in binary mode) or the BSD format.
+			_ => match line.split_once(" (").filter(|(tag, _)| tag.eq_ignore_ascii_case(algorithm.tag())) {
+				Some((_, rest)) => rest.rsplit_once(") = ").map(|(name, checksum)| (name.to_owned(), checksum.to_owned())),
+				None => {
+					let (escaped, line) = line.strip_prefix('\\').map(|l| (true, l)).unwrap_or((false, line));
+
+					line.split_once(' ').map(|(checksum, name)| {
+						let name = name.strip_prefix([' ', '*']).unwrap_or(name);
+						let name = if escaped { unescape(name) } else { name.to_owned() };
+
+						(name, checksum.to_owned())
+					})
+				}
+			},
+		};
+
+		if let Some((name, checksum)) = entry.filter(|(name, checksum)| !name.is_empty() && checksum.len() == algorithm.length() && checksum.chars().all(|c| c.is_ascii_hexdigit())) {
+			entries.push((name, checksum));
+		}
+	}
+
+	entries
+}
+
+/// Reverses the escaping of backslashes and newlines within a name listed within a checksum file.
+fn unescape(name: &str) -> String {
+	let mut unescaped = String::with_capacity(name.len());
+	let mut chars = name.chars();
+
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			unescaped.push(c);
+
+			continue;
+		}
+
+		match chars.next() {
+			Some('n') => unescaped.push('\n'),
+			Some(other) => unescaped.push(other),
+			None => unescaped.push(c),
+		}
+	}
+
+	unescaped
+}
+
+/// Writes a checksum file of the specified algorithm within the specified directory, listing every file within the specified folders (those organised, rather than any others such as the store), where the checksums cached of files which have not changed are reused should they be of the same algorithm.
+pub fn write<T>(dir: T, folders: &[PathBuf], algorithm: Algorithm, cache: Option<&Cache>) -> Result<PathBuf, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let dir = dir.as_ref();
+	let mut files = Vec::new();
+
+	for folder in folders {
+		list(dir, folder, &mut files).map_err(OrganiseError::FailedToListDirectory)?;
+	}
+
+	files.retain(|f| !is_checksum_file(f) && !journal::is_journal(f) && !cache::is_index(f));
+	files.sort();
+	files.dedup();
+
+	let cache = cache.filter(|c| algorithm.hash() == Some(c.algorithm()));
+
+	let lines: Vec<String> = files
+		.par_iter()
+		.map(|relative| {
+			let file = dir.join(relative);
+			let cached = cache.zip(file.metadata().ok()).and_then(|(c, m)| c.get(&file, &m));
+
+			let checksum = match cached {
+				Some(checksum) => checksum,
+				None => {
+					let checksum = algorithm.compute(&file).map_err(OrganiseError::FailedToReadFile)?;
+
+					if let Some(cache) = cache {
+						cache.record(&file, &checksum);
+					}
+
+					checksum
+				}
+			};
+
+			let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+
+			// Names containing a backslash or newline are escaped in the same manner as the coreutils tools, whereas hashdeep never escapes them.
+
+			Ok(if algorithm == Algorithm::Hashdeep {
+				format!("{},{}\n", checksum, name)
+			} else if name.contains(['\\', '\n']) {
+				format!("\\{}  {}\n", checksum, name.replace('\\', "\\\\").replace('\n', "\\n"))
+			} else {
+				format!("{}  {}\n", checksum, name)
+			})
+		})
+		.collect::<Result<Vec<String>, OrganiseError>>()?;
+
+	let path = dir.join(algorithm.file_name());
+
+	// Names within audits are relative to the directory, which is recorded as where the audit was made (as `hashdeep -l` does).
+
+	let header = match algorithm {
+		Algorithm::Hashdeep => {
+			let invoked = fs::canonicalize(dir).map(paths::simplify).unwrap_or_else(|_| dir.to_owned());
+			let command = env::args().collect::<Vec<_>>().join(" ");
+
+			format!("{}\n%%%% size,md5,sha256,filename\n## Invoked from: {}\n## $ {}\n##\n", HASHDEEP_HEADER, invoked.display(), command)
+		}
+		_ => String::new(),
+	};
+
+	File::create(&path).and_then(|mut f| f.write_all((header + &lines.concat()).as_bytes())).map_err(OrganiseError::FailedToWriteChecksums)?;
+
+	Ok(path)
+}
+
+/// Lists the files within the specified folder, relative to the specified root directory.
+fn list(root: &Path, folder: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+	for entry in fs::read_dir(folder)?.flatten() {
+		let kind = entry.file_type()?;
+
+		if links::link(&entry).is_some() {
+			continue;
+		}
+
+		if kind.is_file() {
+			files.push(entry.path().strip_prefix(root).map(Path::to_path_buf).unwrap_or_else(|_| entry.path()));
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::scratch;
+
+	#[test]
+	fn computes_each_algorithm() {
+		let dir = scratch("checksums-compute");
+		let file = dir.join("abc.txt");
+
+		fs::write(&file, "abc").unwrap();
+
+		assert_eq!(Algorithm::Md5.compute(&file).unwrap(), "900150983cd24fb0d6963f7d28e17f72");
+		assert_eq!(Algorithm::Crc32.compute(&file).unwrap(), "352441c2");
+		assert_eq!(
+			Algorithm::Hashdeep.compute(&file).unwrap(),
+			"3,900150983cd24fb0d6963f7d28e17f72,ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+		);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn parses_each_format() {
+		let (md5, sha256) = ("900150983cd24fb0d6963f7d28e17f72", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+		let listing = format!("# comment\n{0}  plain.txt\r\n{0} *binary.txt\n\\{0}  back\\\\slash\\nnewline.txt\nMD5 (bsd (1).txt) = {0}\n{0}  \nnot a checksum  short.txt\n", md5);
+
+		assert_eq!(
+			parse(&listing, Algorithm::Md5),
+			[("plain.txt", md5), ("binary.txt", md5), ("back\\slash\nnewline.txt", md5), ("bsd (1).txt", md5)].map(|(n, c)| (n.to_owned(), c.to_owned()))
+		);
+
+		assert_eq!(parse(&format!("SHA256 (a.txt) = {}\nMD5 (b.txt) = {}\n", sha256, md5), Algorithm::Sha256), [("a.txt".to_owned(), sha256.to_owned())]);
+
+		assert_eq!(
+			parse("; comment\nphotos\\holiday photo.jpg 352441C2\nbroken 3524\n", Algorithm::Crc32),
+			[("photos/holiday photo.jpg".to_owned(), "352441C2".to_owned())]
+		);
+
+		let audit = format!("{}\n%%%% size,md5,sha256,filename\n## Invoked from: /srv\n##\n3,{},{},a, b.txt\n", HASHDEEP_HEADER, md5, sha256);
+
+		assert_eq!(parse(&audit, Algorithm::Hashdeep), [(Path::new("/srv").join("a, b.txt").to_string_lossy().into_owned(), format!("3,{},{}", md5, sha256))]);
+	}
+
+	#[test]
+	fn verifies_what_was_written() {
+		let dir = scratch("checksums-write");
+
+		fs::create_dir(dir.join("sub")).unwrap();
+		fs::create_dir(dir.join("unlisted")).unwrap();
+
+		for (name, contents) in [("a.txt", "one"), ("c d.txt", "two"), ("sub/b.txt", "three"), ("unlisted/e.txt", "four")] {
+			fs::write(dir.join(name), contents).unwrap();
+		}
+
+		// Each listing leaves out the others, as well as any folder not given (such as the store).
+
+		for algorithm in [Algorithm::Md5, Algorithm::Sha256, Algorithm::Hashdeep] {
+			let listing = write(&dir, &[dir.clone(), dir.join("sub")], algorithm, None).unwrap();
+
+			assert_eq!(listing, dir.join(algorithm.file_name()));
+
+			let verifications = verify(&[listing]).unwrap();
+
+			assert_eq!(verifications.len(), 3);
+			assert!(verifications.iter().all(|v| v.outcome == Outcome::Matched));
+		}
+
+		fs::write(dir.join("a.txt"), "changed").unwrap();
+		fs::remove_file(dir.join("sub/b.txt")).unwrap();
+
+		for algorithm in [Algorithm::Md5, Algorithm::Sha256, Algorithm::Hashdeep] {
+			let outcomes: Vec<Outcome> = verify(&[dir.join(algorithm.file_name())]).unwrap().iter().map(|v| v.outcome).collect();
+
+			assert_eq!(outcomes, [Outcome::Mismatched, Outcome::Matched, Outcome::Missing]);
+		}
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,227 @@
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+
+use clap::ValueEnum;
+
+/// Determines what happens to duplicate files once they are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Duplicates {
+	/// Indicates that duplicate files should be permanently deleted.
+	Delete,
+
+	/// Indicates that duplicate files should be moved to the Recycle Bin (or Trash), from where they can be restored.
+	Trash,
+
+	/// Indicates that duplicate files should be moved into a quarantine folder under their original names, from where they can be inspected before being removed.
+	Move,
+
+	/// Indicates that duplicate files should be left in place, with the file system (such as Btrfs or XFS) sharing the contents of the file which is kept with them.
+	#[cfg(target_os = "linux")]
+	Dedupe,
+}
+
+impl Duplicates {
+	/// Returns the verb describing how duplicate files are disposed of.
+	pub fn verb(&self) -> &'static str {
+		match self {
+			Self::Delete => "Deleting",
+			Self::Trash => "Trashing",
+			Self::Move => "Quarantining",
+			#[cfg(target_os = "linux")]
+			Self::Dedupe => "Deduplicating",
+		}
+	}
+
+	/// Returns the action describing how duplicate files are disposed of, as in what would be done to them.
+	pub fn action(&self) -> &'static str {
+		match self {
+			Self::Delete => "delete",
+			Self::Trash => "trash",
+			Self::Move => "quarantine",
+			#[cfg(target_os = "linux")]
+			Self::Dedupe => "deduplicate",
+		}
+	}
+
+	/// Disposes of the specified duplicate file of the specified file which is kept, moving it into the specified quarantine folder should it be quarantined.
+	#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+	pub fn dispose<T, U>(&self, file: T, kept: U, quarantine: Option<&Path>) -> io::Result<()>
+	where
+		T: AsRef<Path>,
+		U: AsRef<Path>,
+	{
+		match self {
+			Self::Delete => fs::remove_file(file),
+			Self::Trash => trash(file.as_ref()),
+			Self::Move => relocate(file.as_ref(), quarantine.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no quarantine folder"))?),
+			#[cfg(target_os = "linux")]
+			Self::Dedupe => dedupe(kept.as_ref(), file.as_ref()),
+		}
+	}
+}
+
+/// Moves the specified file to the Recycle Bin, using the shell so that the deletion honours the settings of the drive and can be undone from Explorer.
+#[cfg(windows)]
+fn trash(file: &Path) -> io::Result<()> {
+	use windows::{
+		core::HSTRING,
+		Win32::{
+			System::Com::{CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_ALL, COINIT_APARTMENTTHREADED, COINIT_DISABLE_OLE1DDE},
+			UI::Shell::{FileOperation, IFileOperation, IShellItem, SHCreateItemFromParsingName, FOFX_RECYCLEONDELETE, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI, FOF_SILENT},
+		},
+	};
+
+	// The shell does not understand verbatim (\\?\) paths, so the path is only made absolute (keeping network paths in their \\server\share form).
+
+	let path = HSTRING::from(crate::paths::simplify(std::path::absolute(file)?).as_os_str());
+
+	// Each worker thread initialises its own apartment, which is only released should this call have been the one to initialise it.
+
+	let initialised = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE) }.is_ok();
+
+	let result = unsafe {
+		(|| -> windows::core::Result<bool> {
+			let operation: IFileOperation = CoCreateInstance(&FileOperation, None, CLSCTX_ALL)?;
+			let item: IShellItem = SHCreateItemFromParsingName(&path, None)?;
+
+			operation.SetOperationFlags(FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT | FOFX_RECYCLEONDELETE)?;
+			operation.DeleteItem(&item, None)?;
+			operation.PerformOperations()?;
+
+			Ok(operation.GetAnyOperationsAborted()?.as_bool())
+		})()
+	};
+
+	if initialised {
+		unsafe { CoUninitialize() };
+	}
+
+	match result {
+		Ok(false) => Ok(()),
+		Ok(true) => Err(io::Error::new(io::ErrorKind::Interrupted, "the deletion was aborted")),
+		Err(e) => Err(io::Error::other(e)),
+	}
+}
+
+/// Held whilst moving a file into quarantine, so that files of the same name being quarantined at once are never given the same number.
+static QUARANTINE: Mutex<()> = Mutex::new(());
+
+/// Moves the specified file into the specified quarantine folder under its original name, numbering it (such as `IMG_0001 (1).jpg`) should a file of that name already have been quarantined.
+fn relocate(file: &Path, quarantine: &Path) -> io::Result<()> {
+	fs::create_dir_all(quarantine)?;
+
+	let name = file.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+	let stem = file.file_stem().unwrap_or(name).to_string_lossy();
+	let numbered = |n: usize| -> PathBuf {
+		match file.extension() {
+			Some(extension) => quarantine.join(format!("{} ({}).{}", stem, n, extension.to_string_lossy())),
+			None => quarantine.join(format!("{} ({})", stem, n)),
+		}
+	};
+
+	let _guard = QUARANTINE.lock().unwrap();
+
+	let target = match quarantine.join(name) {
+		target if !target.exists() => target,
+		_ => (1..).map(numbered).find(|t| !t.exists()).unwrap(),
+	};
+
+	crate::store::relocate(file, target)
+}
+
+/// Moves the specified file to the Trash of the desktop environment.
+#[cfg(not(windows))]
+fn trash(file: &Path) -> io::Result<()> {
+	trash::delete(file).map_err(io::Error::other)
+}
+
+/// The request which shares the extents of one file with another, should their contents be identical (`FIDEDUPERANGE`).
+#[cfg(target_os = "linux")]
+const FIDEDUPERANGE: u64 = 0xc018_9436;
+
+/// The most bytes requested to be deduplicated at once, as file systems may otherwise quietly deduplicate less.
+#[cfg(target_os = "linux")]
+const DEDUPE_LENGTH: u64 = 16 * 1024 * 1024;
+
+/// The status of a request whose ranges were found to differ (`FILE_DEDUPE_RANGE_DIFFERS`).
+#[cfg(target_os = "linux")]
+const DEDUPE_DIFFERS: i32 = 1;
+
+/// Represents a request to deduplicate a range of a file with a single destination (`struct file_dedupe_range`).
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct DedupeRange {
+	src_offset: u64,
+	src_length: u64,
+	dest_count: u16,
+	reserved1: u16,
+	reserved2: u32,
+	info: DedupeRangeInfo,
+}
+
+/// Represents the destination of a request to deduplicate a range of a file (`struct file_dedupe_range_info`).
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct DedupeRangeInfo {
+	dest_fd: i64,
+	dest_offset: u64,
+	bytes_deduped: u64,
+	status: i32,
+	reserved: u32,
+}
+
+/// Shares the extents of the specified file which is kept with the specified duplicate file, which the file system only does once it has compared their contents itself.
+#[cfg(target_os = "linux")]
+fn dedupe(kept: &Path, file: &Path) -> io::Result<()> {
+	use std::{fs::File, os::fd::AsRawFd};
+
+	let source = File::open(kept)?;
+	let length = source.metadata()?.len();
+
+	if file.metadata()?.len() != length {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "the files differ in size"));
+	}
+
+	// Only the owner (or an administrator) may otherwise deduplicate a file which is merely readable.
+
+	let destination = File::options().read(true).write(true).open(file).or_else(|_| File::open(file))?;
+	let mut offset = 0;
+
+	while offset < length {
+		let mut range = DedupeRange {
+			src_offset: offset,
+			src_length: (length - offset).min(DEDUPE_LENGTH),
+			dest_count: 1,
+			reserved1: 0,
+			reserved2: 0,
+			info: DedupeRangeInfo {
+				dest_fd: destination.as_raw_fd() as i64,
+				dest_offset: offset,
+				bytes_deduped: 0,
+				status: 0,
+				reserved: 0,
+			},
+		};
+
+		if unsafe { libc::ioctl(source.as_raw_fd(), FIDEDUPERANGE as libc::Ioctl, &mut range) } < 0 {
+			return Err(io::Error::last_os_error());
+		}
+
+		match range.info.status {
+			DEDUPE_DIFFERS => return Err(io::Error::new(io::ErrorKind::InvalidData, "the contents of the files differ")),
+			s if s < 0 => return Err(io::Error::from_raw_os_error(-s)),
+			_ => (),
+		}
+
+		if range.info.bytes_deduped == 0 {
+			return Err(io::Error::new(io::ErrorKind::WriteZero, "the file system deduplicated nothing"));
+		}
+
+		offset += range.info.bytes_deduped;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,182 @@
+use std::{path::Path, str::FromStr};
+
+use regex::{Regex, RegexBuilder};
+
+use crate::Args;
+
+/// Represents a pattern matching the names of files, in which `*` matches any run of characters, `?` matches any single character and `[...]` matches any of the characters within, ignoring case.
+#[derive(Debug, Clone)]
+pub struct Glob(Regex);
+
+impl FromStr for Glob {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut pattern = String::from("^");
+		let mut chars = s.chars();
+
+		while let Some(c) = chars.next() {
+			match c {
+				'*' => pattern.push_str(".*"),
+				'?' => pattern.push('.'),
+				'[' => {
+					let mut class = String::new();
+
+					// A class which is never closed would otherwise swallow the rest of the pattern, matching none of the names it was meant to.
+
+					loop {
+						match chars.next() {
+							Some(']') => break,
+							Some(c) => class.push(c),
+							None => return Err(format!("unclosed `[` in pattern `{}`", s)),
+						}
+					}
+
+					let (negated, class) = match class.strip_prefix('!') {
+						Some(class) => ("^", class),
+						None => ("", class.as_str()),
+					};
+
+					if class.is_empty() {
+						return Err(format!("invalid pattern `{}`", s));
+					}
+
+					// Ranges (such as `a-z`) keep their meaning, whereas anything else special within a class is taken literally.
+
+					pattern.push_str(&format!("[{}{}]", negated, class.replace('\\', r"\\").replace('[', r"\[").replace('^', r"\^")));
+				}
+				c => pattern.push_str(&regex::escape(&c.to_string())),
+			}
+		}
+
+		pattern.push('$');
+
+		RegexBuilder::new(&pattern).case_insensitive(true).build().map(Self).map_err(|_| format!("invalid pattern `{}`", s))
+	}
+}
+
+impl Glob {
+	/// Returns whether or not the name of the specified file matches this pattern.
+	pub fn matches<T>(&self, file: T) -> bool
+	where
+		T: AsRef<Path>,
+	{
+		file.as_ref().file_name().is_some_and(|n| self.0.is_match(&n.to_string_lossy()))
+	}
+}
+
+/// Represents a size in bytes, such as `500`, `1K`, `1.5M` or `2G`, where each unit is 1024 times the last (and may also be written as `KB` or `KiB`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Size(pub u64);
+
+impl FromStr for Size {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid size `{}`", s);
+
+		let trimmed = s.trim();
+		let (number, unit) = trimmed.split_at(trimmed.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(trimmed.len()));
+
+		let exponent = match unit.trim().to_ascii_uppercase().as_str() {
+			"" | "B" => 0,
+			"K" | "KB" | "KIB" => 1,
+			"M" | "MB" | "MIB" => 2,
+			"G" | "GB" | "GIB" => 3,
+			"T" | "TB" | "TIB" => 4,
+			_ => return Err(invalid()),
+		};
+
+		let number: f64 = number.parse().map_err(|_| invalid())?;
+
+		match number * 1024f64.powi(exponent) {
+			n if n >= 0.0 && n < u64::MAX as f64 => Ok(Self(n.round() as u64)),
+			_ => Err(invalid()),
+		}
+	}
+}
+
+/// Returns whether or not the specified file of the specified size (if known) is to be organised, given the patterns and sizes it must (and must not) satisfy.
+///
+/// A file must match one of the included patterns (should there be any) and none of the excluded patterns, and its size must lie between the minimum and maximum sizes (inclusively), where a file whose size is not known (such as a remote file, or one whose metadata cannot be read) is only judged by its name.
+pub fn admits<T>(file: T, size: Option<u64>, args: &Args) -> bool
+where
+	T: AsRef<Path>,
+{
+	let file = file.as_ref();
+
+	let named = (args.include.is_empty() || args.include.iter().any(|g| g.matches(file))) && !args.exclude.iter().any(|g| g.matches(file));
+
+	named && size.is_none_or(|s| args.min_size.is_none_or(|m| s >= m.0) && args.max_size.is_none_or(|m| s <= m.0))
+}
+
+#[cfg(test)]
+mod tests {
+	use clap::Parser;
+
+	use super::*;
+
+	/// Parses the specified filtering arguments, alongside the defaults of the command line.
+	fn args(arguments: &[&str]) -> Args {
+		Args::parse_from([env!("CARGO_PKG_NAME"), "--dir", "."].iter().chain(arguments))
+	}
+
+	#[test]
+	fn parses_globs() {
+		let glob = |s: &str| s.parse::<Glob>().unwrap();
+
+		assert!(glob("*.jpg").matches("/photos/holiday.JPG"));
+		assert!(!glob("*.jpg").matches("holiday.jpeg"));
+		assert!(glob("img_????.png").matches("IMG_0001.png"));
+		assert!(!glob("img_????.png").matches("img_01.png"));
+		assert!(glob("[a-c]*").matches("banana"));
+		assert!(!glob("[!a-c]*").matches("banana"));
+		assert!(glob("[^]x").matches("^x"));
+		assert!(glob("a.b+c").matches("a.b+c"));
+		assert!(!glob("a.b+c").matches("axbbc"));
+
+		assert!("[]".parse::<Glob>().is_err());
+		assert!("[!]".parse::<Glob>().is_err());
+		assert!("photo[0-9.jpg".parse::<Glob>().is_err());
+	}
+
+	#[test]
+	fn parses_sizes() {
+		let size = |s: &str| s.parse::<Size>().map(|s| s.0);
+
+		assert_eq!(size("500"), Ok(500));
+		assert_eq!(size("500B"), Ok(500));
+		assert_eq!(size("1K"), Ok(1024));
+		assert_eq!(size("1kib"), Ok(1024));
+		assert_eq!(size("1.5M"), Ok(1572864));
+		assert_eq!(size(" 2 GB "), Ok(2147483648));
+		assert_eq!(size("1T"), Ok(1099511627776));
+
+		assert!(size("").is_err());
+		assert!(size("K").is_err());
+		assert!(size("1X").is_err());
+		assert!(size("1.2.3M").is_err());
+		assert!(size("-1K").is_err());
+		assert!(size("99999999999T").is_err());
+	}
+
+	#[test]
+	fn admits_by_name_and_size() {
+		let args = args(&["--include", "*.jpg", "--exclude", "draft*", "--min-size", "1K", "--max-size", "2K"]);
+
+		assert!(admits("photo.JPG", Some(1024), &args));
+		assert!(admits("photo.jpg", Some(2048), &args));
+		assert!(!admits("photo.png", Some(1024), &args));
+		assert!(!admits("draft.jpg", Some(1024), &args));
+		assert!(!admits("photo.jpg", Some(1023), &args));
+		assert!(!admits("photo.jpg", Some(2049), &args));
+	}
+
+	#[test]
+	fn admits_unknown_sizes_by_name_alone() {
+		let args = args(&["--exclude", "*.part", "--min-size", "1K"]);
+
+		assert!(admits("movie.mkv", None, &args));
+		assert!(!admits("movie.mkv.part", None, &args));
+	}
+}
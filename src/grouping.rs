@@ -0,0 +1,167 @@
+use std::{
+	fs::{self, File},
+	io::BufReader,
+	path::{Path, PathBuf},
+	sync::OnceLock,
+};
+
+use clap::ValueEnum;
+use exif::{In, Tag, Value};
+use lofty::{
+	file::{FileType, TaggedFileExt},
+	tag::{Accessor, ItemKey},
+};
+use reverse_geocoder::ReverseGeocoder;
+
+use crate::{paths, session, OrganiseError};
+
+/// Determines how organised files are grouped into folders within the directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+	/// Indicates that audio files should be grouped into artist and album folders using their embedded (ID3, Vorbis or MP4) tags.
+	Tags,
+
+	/// Indicates that photos should be grouped into country and region folders using their embedded (EXIF) location.
+	Location,
+}
+
+/// The number of folders deep (such as artist and album) which organised files are grouped into.
+pub const DEPTH: usize = 2;
+
+/// The name of the folder used in place of a missing artist.
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+
+/// The name of the folder used in place of a missing album.
+const UNKNOWN_ALBUM: &str = "Unknown Album";
+
+/// The name of the folder used in place of a missing country.
+const UNKNOWN_COUNTRY: &str = "Unknown Country";
+
+/// The name of the folder used in place of a missing region.
+const UNKNOWN_REGION: &str = "Unknown Region";
+
+/// The names of devices (besides those numbered, such as `COM1`) which cannot name files or folders on Windows.
+const RESERVED_NAMES: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+/// Returns the folder (relative to the directory) into which the specified file should be organised, or nothing if it should remain in place.
+pub fn folder<T>(group_by: GroupBy, file: T) -> Result<Option<PathBuf>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	match group_by {
+		GroupBy::Tags => tags_folder(file),
+		GroupBy::Location => location_folder(file),
+	}
+}
+
+/// Returns the artist and album folder of the specified audio file from its embedded tags.
+fn tags_folder<T>(file: T) -> Result<Option<PathBuf>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	if FileType::from_path(&file).is_none() {
+		return Ok(None);
+	}
+
+	let tagged = lofty::read_from_path(&file).map_err(OrganiseError::FailedToReadTags)?;
+	let tag = tagged.primary_tag().or_else(|| tagged.first_tag());
+
+	// The album artist is preferred so that compilations are not split across many artist folders.
+
+	let artist = tag.and_then(|t| t.get_string(ItemKey::AlbumArtist).map(str::to_owned).or_else(|| t.artist().map(|a| a.into_owned())));
+	let album = tag.and_then(|t| t.album().map(|a| a.into_owned()));
+
+	let artist = artist.as_deref().map(sanitise).filter(|a| !a.is_empty()).unwrap_or_else(|| UNKNOWN_ARTIST.to_owned());
+	let album = album.as_deref().map(sanitise).filter(|a| !a.is_empty()).unwrap_or_else(|| UNKNOWN_ALBUM.to_owned());
+
+	Ok(Some(PathBuf::from(artist).join(album)))
+}
+
+/// Returns the country and region folder of the specified photo from its embedded location, using an offline table of places.
+fn location_folder<T>(file: T) -> Result<Option<PathBuf>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	static GEOCODER: OnceLock<ReverseGeocoder> = OnceLock::new();
+
+	// Files without any location (or any metadata at all) remain in place.
+
+	let Ok(exif) = File::open(file).map(BufReader::new).map_err(exif::Error::Io).and_then(|mut r| exif::Reader::new().read_from_container(&mut r)) else {
+		return Ok(None);
+	};
+
+	let coordinate = |value: Tag, reference: Tag, negative: &str| -> Option<f64> {
+		let Value::Rational(parts) = &exif.get_field(value, In::PRIMARY)?.value else {
+			return None;
+		};
+
+		let degrees = parts.iter().zip([1.0, 60.0, 3600.0]).map(|(p, d)| p.to_f64() / d).sum::<f64>();
+		let reference = exif.get_field(reference, In::PRIMARY)?.display_value().to_string();
+
+		Some(if reference.eq_ignore_ascii_case(negative) { -degrees } else { degrees })
+	};
+
+	let (Some(latitude), Some(longitude)) = (coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"), coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")) else {
+		return Ok(None);
+	};
+
+	let place = GEOCODER.get_or_init(ReverseGeocoder::new).search((latitude, longitude)).record;
+
+	let country = Some(sanitise(&place.cc)).filter(|c| !c.is_empty()).unwrap_or_else(|| UNKNOWN_COUNTRY.to_owned());
+	let region = Some(sanitise(&place.admin1)).filter(|r| !r.is_empty()).unwrap_or_else(|| UNKNOWN_REGION.to_owned());
+
+	Ok(Some(PathBuf::from(country).join(region)))
+}
+
+/// Sanitises the specified text for use as the name of a folder on any common file system.
+pub fn sanitise(text: &str) -> String {
+	let sanitised: String = text
+		.chars()
+		.map(|c| match c {
+			'/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+			c if c.is_control() => '_',
+			c => c,
+		})
+		.collect();
+
+	let sanitised = sanitised.trim().trim_end_matches('.').trim_end();
+
+	// Names of devices are reserved by Windows (and so upon FAT and exFAT drives written by it) whatever their extension.
+
+	let device = sanitised.split('.').next().unwrap_or_default().trim_end().to_ascii_uppercase();
+
+	if !(RESERVED_NAMES.contains(&device.as_str()) || ((device.starts_with("COM") || device.starts_with("LPT")) && device.len() == 4 && device.ends_with(|c: char| c.is_ascii_digit() && c != '0'))) {
+		return sanitised.to_owned();
+	}
+
+	// The name of the device is suffixed rather than the extension (such as `CON_.txt` rather than `CON.txt_`), as it is the name before the first dot which is reserved.
+
+	let (stem, rest) = sanitised.split_at(sanitised.find('.').unwrap_or(sanitised.len()));
+
+	format!("{}_{}", stem, rest)
+}
+
+/// Resolves the specified folder (relative to the specified directory) against those which already exist or have been chosen during the current session, so that folders differing only by case or composition (such as `ABBA` and `Abba`) are merged into whichever came first, as they are upon case-insensitive file systems anyway.
+pub fn resolve<T>(dir: T, folder: &Path) -> PathBuf
+where
+	T: AsRef<Path>,
+{
+	let session = session::current();
+	let mut groups = session.groups.lock().unwrap();
+	let mut resolved = PathBuf::new();
+
+	for component in folder.iter() {
+		let parent = dir.as_ref().join(&resolved);
+		let folded = paths::fold(component);
+
+		let name = groups.entry(parent.join(&folded)).or_insert_with(|| {
+			let existing = fs::read_dir(&parent).into_iter().flatten().flatten().map(|e| e.file_name()).find(|n| paths::fold(n) == folded);
+
+			existing.unwrap_or_else(|| component.to_owned())
+		});
+
+		resolved.push(name);
+	}
+
+	resolved
+}
@@ -0,0 +1,132 @@
+use std::{
+	fs::File,
+	io::{self, Read},
+	path::Path,
+};
+
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::storage;
+
+/// The size of the chunks in which files are read whilst being hashed.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Determines the algorithm whose checksums organised files are named by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Hash {
+	/// Indicates MD5, as files have always been named by.
+	Md5,
+
+	/// Indicates SHA-256.
+	Sha256,
+
+	/// Indicates BLAKE3, which is far faster than SHA-256 with the same length.
+	Blake3,
+
+	/// Indicates the 64-bit variant of XXH3, which is fastest of all but only resists accidental collisions.
+	Xxh3,
+}
+
+impl Hash {
+	/// Returns the length of a checksum produced by this algorithm, in hexadecimal digits.
+	pub fn length(&self) -> usize {
+		match self {
+			Self::Md5 => 32,
+			Self::Sha256 | Self::Blake3 => 64,
+			Self::Xxh3 => 16,
+		}
+	}
+
+	/// Computes the (hexadecimal) checksum of the contents of the specified file using this algorithm, reading it in chunks rather than all at once.
+	pub fn compute<T>(&self, file: T) -> io::Result<String>
+	where
+		T: AsRef<Path>,
+	{
+		let mut hasher = Hasher::new(*self);
+
+		read(file, |chunk| hasher.update(chunk))?;
+
+		Ok(hasher.finish())
+	}
+
+	/// Returns whether or not the specified name (without its extension) appears to be a checksum produced by this algorithm, as the names of organised files are.
+	pub fn is_checksum(&self, name: &str) -> bool {
+		name.len() == self.length() && name.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+	}
+}
+
+/// Reads the contents of the specified file in chunks rather than all at once, giving each chunk to the specified function in turn, returning the size of the file.
+pub fn read<T, F>(file: T, mut consume: F) -> io::Result<u64>
+where
+	T: AsRef<Path>,
+	F: FnMut(&[u8]),
+{
+	storage::gated(|| {
+		let mut handle = File::open(file)?;
+		let mut buffer = vec![0u8; CHUNK_SIZE];
+		let mut size = 0u64;
+
+		loop {
+			let read = handle.read(&mut buffer)?;
+
+			if read == 0 {
+				return Ok(size);
+			}
+
+			size += read as u64;
+
+			consume(&buffer[..read]);
+		}
+	})
+}
+
+/// Represents a checksum being computed by a particular algorithm, holding only the state of that algorithm.
+pub enum Hasher {
+	/// Indicates a checksum being computed by MD5.
+	Md5(md5::Context),
+
+	/// Indicates a checksum being computed by SHA-256.
+	Sha256(Sha256),
+
+	/// Indicates a checksum being computed by BLAKE3, whose state is large enough to be kept upon the heap.
+	Blake3(Box<blake3::Hasher>),
+
+	/// Indicates a checksum being computed by XXH3, whose state is large enough to be kept upon the heap.
+	Xxh3(Box<Xxh3>),
+}
+
+impl Hasher {
+	/// Creates a new hasher computing a checksum by the specified algorithm.
+	pub fn new(algorithm: Hash) -> Self {
+		match algorithm {
+			Hash::Md5 => Self::Md5(md5::Context::new()),
+			Hash::Sha256 => Self::Sha256(Sha256::new()),
+			Hash::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+			Hash::Xxh3 => Self::Xxh3(Box::new(Xxh3::new())),
+		}
+	}
+
+	/// Adds the specified data to the checksum.
+	pub fn update(&mut self, data: &[u8]) {
+		match self {
+			Self::Md5(md5) => md5.consume(data),
+			Self::Sha256(sha256) => sha256.update(data),
+			Self::Blake3(blake3) => {
+				blake3.update(data);
+			}
+			Self::Xxh3(xxh3) => xxh3.update(data),
+		}
+	}
+
+	/// Returns the (hexadecimal) checksum of all of the data added.
+	pub fn finish(self) -> String {
+		match self {
+			Self::Md5(md5) => format!("{:x}", md5.compute()),
+			Self::Sha256(sha256) => sha256.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+			Self::Blake3(blake3) => blake3.finalize().to_hex().to_string(),
+			Self::Xxh3(xxh3) => format!("{:016x}", xxh3.digest()),
+		}
+	}
+}
@@ -0,0 +1,208 @@
+use std::{
+	collections::BTreeMap,
+	env,
+	fs::{self, File},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{dates, session::{self, Session}, OrganiseError};
+
+/// The name of the file within the data directory listing every run.
+const HISTORY_FILE: &str = "history";
+
+/// Represents the files organised and duplicates disposed of, either in total or of a particular kind (such as with a particular extension).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tally {
+	/// The number of files organised (renamed).
+	pub organised: usize,
+
+	/// The number of duplicate files disposed of.
+	pub duplicates: usize,
+
+	/// The number of bytes held by the duplicate files disposed of.
+	pub reclaimed: u64,
+
+	/// The number of files which failed to be organised.
+	pub failed: usize,
+}
+
+/// Represents a past run of organising a directory.
+#[derive(Debug, Clone)]
+pub struct Run {
+	/// When the run started, in seconds since the Unix epoch.
+	pub started: u64,
+
+	/// How long the run took.
+	pub duration: Duration,
+
+	/// The number of files organised (renamed).
+	pub organised: usize,
+
+	/// The number of duplicate files disposed of.
+	pub duplicates: usize,
+
+	/// The number of bytes held by the duplicate files disposed of.
+	pub reclaimed: u64,
+
+	/// The outcome of the run, which is either `succeeded` or `failed` along with the error it failed with.
+	pub outcome: String,
+
+	/// The arguments the run was started with.
+	pub arguments: String,
+}
+
+impl Run {
+	/// Returns when this run started, as a date and time in UTC.
+	pub fn date(&self) -> String {
+		let (year, month, day) = dates::civil((self.started / 86400) as i64);
+		let seconds = self.started % 86400;
+
+		format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+	}
+}
+
+impl Tally {
+	/// Adds the specified tally to this one.
+	fn add(&mut self, other: Tally) {
+		self.organised += other.organised;
+		self.duplicates += other.duplicates;
+		self.reclaimed += other.reclaimed;
+		self.failed += other.failed;
+	}
+}
+
+/// Counts the specified tally against the extension and folder of the specified file.
+fn count(file: &Path, tally: Tally) {
+	let extension = file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+	let folder = file.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_owned();
+
+	let session = session::current();
+
+	session.extensions.lock().unwrap().entry(extension).or_default().add(tally);
+	session.folders.lock().unwrap().entry(folder).or_default().add(tally);
+}
+
+/// Counts the files organised and duplicates disposed of during the specified session (such as that of a job) towards the current one as well.
+pub fn absorb(other: &Session) {
+	let session = session::current();
+
+	for (extension, tally) in other.extensions.lock().unwrap().iter() {
+		session.extensions.lock().unwrap().entry(extension.clone()).or_default().add(*tally);
+	}
+
+	for (folder, tally) in other.folders.lock().unwrap().iter() {
+		session.folders.lock().unwrap().entry(folder.clone()).or_default().add(*tally);
+	}
+}
+
+/// Counts the specified file as having been organised during this run.
+pub fn organised(file: &Path) {
+	count(file, Tally { organised: 1, ..Default::default() });
+}
+
+/// Counts the specified duplicate file of the specified size as having been disposed of during this run.
+pub fn disposed(file: &Path, size: u64) {
+	count(file, Tally { duplicates: 1, reclaimed: size, ..Default::default() });
+}
+
+/// Counts the specified file as having failed to be organised during this run.
+pub fn failed(file: &Path) {
+	count(file, Tally { failed: 1, ..Default::default() });
+}
+
+/// Returns the files organised and duplicates disposed of during this run, in total.
+pub fn total() -> Tally {
+	session::current().extensions.lock().unwrap().values().fold(Tally::default(), |mut total, t| {
+		total.add(*t);
+		total
+	})
+}
+
+/// Returns the files organised and duplicates disposed of during this run, by the (lowercase) extension of each file, where files without an extension have an empty one.
+pub fn by_extension() -> BTreeMap<String, Tally> {
+	session::current().extensions.lock().unwrap().clone()
+}
+
+/// Returns the files organised and duplicates disposed of during this run, by the folder holding each file.
+pub fn by_folder() -> BTreeMap<PathBuf, Tally> {
+	session::current().folders.lock().unwrap().clone()
+}
+
+/// Returns the path of the file listing every run, within the conventional data directory of the user.
+fn file() -> io::Result<PathBuf> {
+	#[cfg(windows)]
+	let data = env::var_os("LOCALAPPDATA").map(PathBuf::from);
+
+	#[cfg(target_os = "macos")]
+	let data = env::var_os("HOME").map(|h| PathBuf::from(h).join("Library").join("Application Support"));
+
+	#[cfg(all(unix, not(target_os = "macos")))]
+	let data = env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()).map(PathBuf::from).or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")));
+
+	data.map(|d| d.join("directory-organiser").join(HISTORY_FILE)).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))
+}
+
+/// Records this run within the history, as having started at the specified time and finished (now) with the specified result.
+pub fn record(started: SystemTime, result: &Result<(), OrganiseError>) -> io::Result<()> {
+	let file = file()?;
+
+	if let Some(parent) = file.parent() {
+		fs::create_dir_all(parent)?;
+	}
+
+	// Arguments and errors are written upon a single line of tab-separated columns, so neither may contain tabs or new lines of their own.
+
+	let clean = |s: String| s.replace(['\t', '\r', '\n'], " ");
+
+	let outcome = match result {
+		Ok(()) => "succeeded".to_owned(),
+		Err(e) => clean(format!("failed ({})", e)),
+	};
+	let arguments = clean(env::args().skip(1).collect::<Vec<_>>().join(" "));
+
+	let total = total();
+	let line = format!(
+		"{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+		started.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+		started.elapsed().map(|d| d.as_millis()).unwrap_or_default(),
+		total.organised,
+		total.duplicates,
+		total.reclaimed,
+		outcome,
+		arguments
+	);
+
+	File::options().create(true).append(true).open(file)?.write_all(line.as_bytes())
+}
+
+/// Reads every run within the history, from the earliest to the latest, where a history which does not yet exist has no runs.
+pub fn runs() -> Result<Vec<Run>, OrganiseError> {
+	let contents = match fs::read_to_string(file().map_err(OrganiseError::FailedToReadFile)?) {
+		Ok(contents) => contents,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(OrganiseError::FailedToReadFile(e)),
+	};
+
+	Ok(contents
+		.lines()
+		.filter_map(|line| {
+			let columns: Vec<&str> = line.splitn(7, '\t').collect();
+
+			let [started, duration, organised, duplicates, reclaimed, outcome, arguments] = columns[..] else {
+				return None;
+			};
+
+			Some(Run {
+				started: started.parse().ok()?,
+				duration: Duration::from_millis(duration.parse().ok()?),
+				organised: organised.parse().ok()?,
+				duplicates: duplicates.parse().ok()?,
+				reclaimed: reclaimed.parse().ok()?,
+				outcome: outcome.to_owned(),
+				arguments: arguments.to_owned(),
+			})
+		})
+		.collect())
+}
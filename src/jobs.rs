@@ -0,0 +1,72 @@
+use std::{
+	ffi::OsString,
+	fs, io,
+	path::Path,
+};
+
+use clap::Parser;
+
+use crate::{Args, OrganiseError};
+
+/// Represents a particular job within a jobs file, organising a directory in its own way.
+#[derive(Debug)]
+pub struct Job {
+	/// The name of the job, as given by the heading of its section.
+	pub name: String,
+
+	/// The arguments which the job organises with.
+	pub args: Args,
+}
+
+/// Reads the jobs within the specified jobs file, in the order they are listed.
+///
+/// Each job is a section headed by its name in brackets (such as `[photos]`), followed by the options it organises with upon lines of their own, named as upon the command line but without their leading dashes. Options which take values are given them after an equals sign (such as `dir = /srv/photos`) and may be repeated, whereas flags are given alone (such as `sidecars`). Blank lines and lines beginning with `#` are ignored.
+pub fn read<T>(file: T) -> Result<Vec<Job>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let contents = fs::read_to_string(file).map_err(OrganiseError::FailedToReadJobs)?;
+	let invalid = |line: usize, reason: String| OrganiseError::FailedToReadJobs(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, reason)));
+
+	let mut sections: Vec<(String, usize, Vec<OsString>)> = Vec::new();
+
+	for (number, line) in contents.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+			sections.push((name.trim().to_owned(), number, vec![OsString::from(env!("CARGO_PKG_NAME"))]));
+
+			continue;
+		}
+
+		let Some((_, _, arguments)) = sections.last_mut() else {
+			return Err(invalid(number, "option outside of a job".to_owned()));
+		};
+
+		// Each option is passed exactly as it would be upon the command line, so that jobs accept the same options (and are validated in the same way) as a single run.
+
+		match line.split_once('=') {
+			Some((key, value)) => {
+				arguments.push(format!("--{}", key.trim()).into());
+				arguments.push(value.trim().into());
+			}
+			None => arguments.push(format!("--{}", line).into()),
+		}
+	}
+
+	sections
+		.into_iter()
+		.map(|(name, number, arguments)| match Args::try_parse_from(arguments) {
+			Ok(args) => Ok(Job { name, args }),
+			Err(e) => {
+				let reason = e.to_string();
+				let reason = reason.lines().take_while(|l| !l.is_empty()).map(str::trim).collect::<Vec<_>>().join(" ");
+				let reason = reason.trim_start_matches("error: ");
+
+				Err(invalid(number, format!("job <{}> is invalid: {}", name, reason)))
+			}
+		})
+		.collect()
+}
@@ -0,0 +1,222 @@
+use std::{
+	fs::{self, File},
+	io::{self, Write},
+	path::{self, Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+	output::{self, say, warning},
+	remote, session, OrganiseError,
+};
+
+/// The prefix of the name of each journal written within the directory, which is followed by when the run started.
+const JOURNAL_PREFIX: &str = ".organiser-journal-";
+
+/// The extension of the name of each journal, whose entries are each written as a line of JSON.
+const JOURNAL_EXTENSION: &str = "json";
+
+/// Determines a particular operation recorded within a journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+	/// Indicates that the file was renamed to the specified path.
+	Rename(PathBuf),
+
+	/// Indicates that the file was removed as a duplicate of the specified file, or as an empty file should there be none.
+	Remove(Option<PathBuf>),
+}
+
+/// Represents a particular entry within a journal.
+#[derive(Debug, Clone)]
+pub struct Entry {
+	/// The (absolute) path of the file as it was before the operation.
+	pub file: PathBuf,
+
+	/// The operation performed upon the file.
+	pub operation: Operation,
+}
+
+/// Opens a journal within the specified directory for the rest of the current session, which is only written once there is something to record, where a journal already opened during the session continues to be used instead.
+pub fn open<T>(dir: T)
+where
+	T: AsRef<Path>,
+{
+	let started = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+	let path = dir.as_ref().join(format!("{}{}.{}", JOURNAL_PREFIX, started, JOURNAL_EXTENSION));
+
+	session::current().journal.lock().unwrap().get_or_insert_with(|| (path::absolute(&path).unwrap_or(path), None));
+}
+
+/// Returns whether or not the specified file is a journal, which is never organised itself.
+pub fn is_journal<T>(file: T) -> bool
+where
+	T: AsRef<Path>,
+{
+	file.as_ref().file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(JOURNAL_PREFIX))
+}
+
+/// Records that the specified file with the specified checksum was renamed to the specified path, should a journal be open.
+pub fn renamed<T, U>(file: T, to: U, checksum: &str)
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	append(file.as_ref(), "rename", Some(to.as_ref()), checksum);
+}
+
+/// Records that the specified file with the specified checksum was removed as a duplicate of the specified file (or as an empty file, should there be none), should a journal be open.
+pub fn removed<T>(file: T, kept: Option<&Path>, checksum: &str)
+where
+	T: AsRef<Path>,
+{
+	append(file.as_ref(), "remove", kept, checksum);
+}
+
+/// Appends an entry for the specified operation to the journal, should one be open.
+fn append(file: &Path, operation: &str, target: Option<&Path>, checksum: &str) {
+	let session = session::current();
+	let mut journal = session.journal.lock().unwrap();
+
+	let Some((path, handle)) = journal.as_mut() else {
+		return;
+	};
+
+	if handle.is_none() {
+		match File::options().create(true).append(true).open(&path) {
+			Ok(file) => {
+				say!("Journalling to <{}>.", path.display());
+
+				*handle = Some(file);
+			}
+			Err(e) => {
+				warning!("Failed to create journal <{}>: {}.", path.display(), e);
+
+				return;
+			}
+		}
+	}
+
+	// Paths are recorded absolutely (with remote locations already being so), so that the journal may be undone from anywhere, and each entry is written as soon as it is made, so that a run which is interrupted may still be undone.
+
+	let absolute = |p: &Path| match remote::is_remote(p) {
+		true => p.to_owned(),
+		false => path::absolute(p).unwrap_or_else(|_| p.to_owned()),
+	};
+
+	let line = format!(
+		"{{\"time\":{},\"operation\":{},\"file\":{},\"target\":{},\"checksum\":{}}}\n",
+		SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+		output::quote(operation),
+		output::quote(&absolute(file).display().to_string()),
+		target.map_or("null".to_owned(), |t| output::quote(&absolute(t).display().to_string())),
+		output::quote(checksum)
+	);
+
+	if let Err(e) = handle.as_mut().unwrap().write_all(line.as_bytes()) {
+		warning!("Failed to write entry to journal <{}>: {}.", path.display(), e);
+	}
+}
+
+/// Reads every entry within the specified journal, in the order the operations were performed, where each line which is not a valid entry (such as one cut short by an interrupted run) is read as an error of its own rather than failing the journal as a whole.
+pub fn read<T>(journal: T) -> Result<Vec<io::Result<Entry>>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let contents = fs::read_to_string(journal).map_err(OrganiseError::FailedToReadJournal)?;
+
+	Ok(contents
+		.lines()
+		.enumerate()
+		.filter(|(_, l)| !l.trim().is_empty())
+		.map(|(number, line)| parse(line).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("line {} is invalid", number + 1))))
+		.collect())
+}
+
+/// Parses the specified line of a journal as an entry.
+fn parse(line: &str) -> Option<Entry> {
+	let fields = output::fields(line)?;
+
+	// The time and checksum of each operation serve only as a record of what was done, so are not needed to undo it.
+
+	let operation = match (fields.get("operation")?.as_str(), fields.get("target")) {
+		("rename", Some(target)) => Operation::Rename(PathBuf::from(target)),
+		("remove", target) => Operation::Remove(target.map(PathBuf::from)),
+		_ => return None,
+	};
+
+	Some(Entry { file: PathBuf::from(fields.get("file")?), operation })
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::{
+		output::{Output, Verbosity},
+		session::Session,
+		testing::scratch,
+	};
+
+	/// Journals the specified operations within the specified directory, within a session of its own, returning the journal written.
+	fn journal<F>(dir: &Path, operations: F) -> PathBuf
+	where
+		F: FnOnce() + Send,
+	{
+		let session = Arc::new(Session::new(Output::Text, Verbosity::Silent));
+
+		session.run(Some(1), || {
+			open(dir);
+			operations();
+		})
+		.unwrap();
+
+		let journal = session.journal.lock().unwrap().as_ref().map(|(p, _)| p.to_owned());
+
+		journal.unwrap()
+	}
+
+	#[test]
+	fn reads_what_was_written() {
+		let dir = scratch("journal-read");
+		let (odd, kept) = (dir.join("a\tb\nc \"d\".txt"), dir.join("e.txt"));
+
+		let journal = journal(&dir, || {
+			renamed(&odd, &kept, "e");
+			removed(dir.join("f.txt"), Some(&kept), "e");
+			removed(dir.join("g.txt"), None, "");
+		});
+
+		let entries: Vec<Entry> = read(&journal).unwrap().into_iter().map(Result::unwrap).collect();
+
+		assert!(is_journal(&journal));
+		assert_eq!(entries.len(), 3);
+		assert_eq!((&entries[0].file, &entries[0].operation), (&odd, &Operation::Rename(kept.clone())));
+		assert_eq!((&entries[1].file, &entries[1].operation), (&dir.join("f.txt"), &Operation::Remove(Some(kept))));
+		assert_eq!((&entries[2].file, &entries[2].operation), (&dir.join("g.txt"), &Operation::Remove(None)));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn reads_around_invalid_lines() {
+		let dir = scratch("journal-invalid");
+
+		let journal = journal(&dir, || renamed(dir.join("a.txt"), dir.join("b.txt"), "b"));
+		let mut contents = fs::read_to_string(&journal).unwrap();
+
+		contents.push_str("{\"time\":1,\"operation\":\"rename\",\"file\":\"/c.txt\"}\n{\"time\":1,\"operation\":\"rena\n");
+		contents.push_str(&contents.clone().lines().next().unwrap().replace("a.txt", "c.txt"));
+
+		fs::write(&journal, contents).unwrap();
+
+		let entries = read(&journal).unwrap();
+
+		assert_eq!(entries.len(), 4);
+		assert!(entries[0].is_ok() && entries[3].is_ok());
+		assert!(entries[1].is_err() && entries[2].is_err());
+		assert_eq!(entries[3].as_ref().unwrap().file, dir.join("c.txt"));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,1829 @@
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	error::Error,
+	fmt::{self, Display},
+	fs::{self, File},
+	io,
+	path::{Path, PathBuf},
+	process::ExitCode,
+	sync::Arc,
+	time::{Duration, Instant, SystemTime},
+};
+
+use cache::Cache;
+pub use changes::Change;
+use changes::{ChangeFormat, ChangeList};
+use checksums::{Algorithm, Outcome};
+use clap::{Parser, Subcommand, ValueEnum};
+use duplicates::Duplicates;
+pub use filter::{Glob, Size};
+use grouping::GroupBy;
+pub use hashing::Hash;
+use journal::Operation;
+use image::ImageFormat;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use normalise::Normalisation;
+pub use organiser::{Organiser, Report};
+use output::{detail, say, warning};
+pub use output::{Event, Output, Verbosity};
+use policy::{EmptyFiles, Keep};
+use regex::Regex;
+use sample::{Percentage, Sample};
+use session::Session;
+use sidecar::Sidecars;
+use similarity::Similarity;
+use store::Store;
+
+mod annex;
+mod archive;
+mod attributes;
+mod cache;
+mod changes;
+mod checksums;
+mod dates;
+mod duplicates;
+mod filter;
+mod grouping;
+mod hashing;
+mod history;
+mod jobs;
+mod journal;
+mod links;
+mod normalise;
+mod organiser;
+mod output;
+mod paths;
+mod plan;
+mod policy;
+mod protection;
+mod remote;
+mod reports;
+mod sample;
+mod session;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(windows)]
+mod shadow;
+mod sidecar;
+#[cfg(feature = "sftp")]
+mod sftp;
+mod similarity;
+mod storage;
+mod store;
+#[cfg(test)]
+mod testing;
+mod torrent;
+#[cfg(all(feature = "fuse", any(target_os = "linux", target_os = "macos")))]
+mod view;
+mod watch;
+#[cfg(feature = "webdav")]
+mod webdav;
+
+/// Performs organisation on directories.
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about, subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
+pub struct Args {
+	/// Specifies the command to perform instead of organising
+	#[command(subcommand)]
+	command: Option<Command>,
+
+	/// Specifies the directory to organise, which may also be a remote location (such as sftp://user@host/path, s3://bucket/prefix or davs://user@host/path)
+	#[arg(short, long, required_unless_present = "from_report")]
+	dir: Option<PathBuf>,
+
+	/// Specifies to organise the files within every folder beneath the directory as well, each in place within its own folder
+	#[arg(short, long)]
+	recursive: bool,
+
+	/// Specifies the deepest level of folders beneath the directory to organise, where the directory itself is at a depth of zero
+	#[arg(long, requires = "recursive")]
+	max_depth: Option<usize>,
+
+	/// Specifies a pattern (such as `*.jpg`) matching the names of the only files to organise, ignoring case, which may be given several times
+	#[arg(long)]
+	include: Vec<Glob>,
+
+	/// Specifies a pattern (such as `*.part`) matching the names of files to leave alone, ignoring case, which may be given several times
+	#[arg(long)]
+	exclude: Vec<Glob>,
+
+	/// Specifies the size of the smallest files to organise (such as 1K)
+	#[arg(long)]
+	min_size: Option<Size>,
+
+	/// Specifies the size of the largest files to organise (such as 2G)
+	#[arg(long)]
+	max_size: Option<Size>,
+
+	/// Specifies a report of duplicate files (from fdupes, rdfind or fclones) to organise the groups of, instead of searching a directory
+	#[arg(long, conflicts_with = "change_list")]
+	from_report: Option<PathBuf>,
+
+	/// Specifies an additional directory to refuse to organise (along with every directory beneath it) without forcing, which may also be listed within the DIRECTORY_ORGANISER_PROTECT environment variable
+	#[arg(long)]
+	protect: Vec<PathBuf>,
+
+	/// Specifies to organise the directory even should it be protected (such as a system directory or the home directory)
+	#[arg(long)]
+	force: bool,
+
+	/// Specifies the organisation mode
+	#[arg(short, long, value_enum, default_value_t=Mode::Fast)]
+	mode: Mode,
+
+	/// Specifies the algorithm whose checksums files are named by (and recognised as organised by, in fast mode), where files compared by their normalised content are always named by MD5
+	#[arg(short, long, value_enum, default_value_t=Hash::Md5)]
+	algorithm: Hash,
+
+	/// Specifies which of two duplicate files to keep
+	#[arg(short, long, value_enum, default_value_t=Keep::Existing)]
+	keep: Keep,
+
+	/// Specifies a pattern (as a regular expression) matching the paths of duplicate files to keep in preference to others, regardless of which to keep otherwise, where earlier patterns are preferred over later ones (only duplicates within the same folder are compared, even when organising recursively, except in reports of duplicates across merged directories)
+	#[arg(long)]
+	prefer: Vec<Regex>,
+
+	/// Specifies what to do with duplicate files
+	#[arg(long, value_enum, default_value_t=Duplicates::Delete)]
+	duplicates: Duplicates,
+
+	/// Specifies the folder to move duplicate files into when quarantining them
+	#[arg(long, required_if_eq("duplicates", "move"))]
+	quarantine: Option<PathBuf>,
+
+	/// Specifies what to do with empty (zero-byte) files, which are usually leftover placeholders but occasionally meaningful markers
+	#[arg(long, value_enum, default_value_t=EmptyFiles::Organise)]
+	empty_files: EmptyFiles,
+
+	/// Specifies to remove empty files which were last modified more than the specified number of days ago, in the same way as duplicate files
+	#[arg(long)]
+	remove_empty_older_than: Option<u64>,
+
+	/// Specifies the kinds of files to compare by their normalised content
+	#[arg(long, value_enum)]
+	normalise: Vec<Normalisation>,
+
+	/// Specifies the kinds of near-duplicate content to report
+	#[arg(long, value_enum)]
+	similar: Vec<Similarity>,
+
+	/// Specifies to report loose files whose contents already exist within an archive
+	#[arg(long)]
+	report_archived: bool,
+
+	/// Specifies to move companion (sidecar) files together with the file they accompany
+	#[arg(long)]
+	sidecars: bool,
+
+	/// Specifies how to group organised files into folders beneath the directory, including those found within folders beneath it when organising recursively
+	#[arg(short, long, value_enum)]
+	group_by: Option<GroupBy>,
+
+	/// Specifies the number of seconds since a file was last modified before it is organised, so that files still being written (such as downloads in progress) are left until a later run
+	#[arg(long, default_value_t = 10)]
+	settle_time: u64,
+
+	/// Specifies to verify files against existing checksum files (such as SFV, md5sum and sha256sum files) before organising
+	#[arg(long)]
+	verify_checksums: bool,
+
+	/// Specifies to leave files seeded by .torrent files (within the directory or the seeding directory) in place
+	#[arg(long)]
+	protect_torrents: bool,
+
+	/// Specifies an additional directory containing the .torrent files being seeded from the directory
+	#[arg(long, requires = "protect_torrents")]
+	seeding_dir: Option<PathBuf>,
+
+	/// Specifies to verify the protected files against the piece hashes of their torrents
+	#[arg(long, requires = "protect_torrents")]
+	verify_torrents: bool,
+
+	/// Specifies a content-addressable store to move content into, leaving links to it in place of organised files
+	#[arg(long)]
+	store: Option<PathBuf>,
+
+	/// Specifies how to handle the SELinux security contexts of content moved into the store
+	#[cfg(all(target_os = "linux", feature = "selinux"))]
+	#[arg(long, value_enum, requires = "store")]
+	selinux: Option<attributes::Labelling>,
+
+	/// Specifies to compute checksums of remote files by running md5sum on the server, rather than streaming their contents
+	#[cfg(feature = "sftp")]
+	#[arg(long)]
+	remote_hash: bool,
+
+	/// Specifies to hash (and report) files held open by other applications through a shadow copy of their volume, deferring their renames until they are no longer in use, which requires elevation
+	#[cfg(windows)]
+	#[arg(long)]
+	shadow_copy: bool,
+
+	/// Specifies to clear the quarantine of organised files (and their duplicates), rather than preserving it so that Gatekeeper still checks them when first opened
+	#[cfg(target_os = "macos")]
+	#[arg(long)]
+	clear_quarantine: bool,
+
+	/// Specifies to name organised files by their git-annex (MD5E) keys, such as MD5E-s1024--d41d8cd98f00b204e9800998ecf8427e.jpg
+	#[arg(long, conflicts_with_all = ["normalise", "algorithm"])]
+	annex: bool,
+
+	/// Specifies to write a checksum file (such as `MD5SUMS`) listing every organised file, by the algorithm files are named by unless another is given
+	#[arg(long, value_enum, num_args = 0..=1)]
+	write_checksums: Option<Option<Algorithm>>,
+
+	/// Specifies a file to write the renames and removals to, as a script which replays them upon a mirror of the directory
+	#[arg(long)]
+	change_list: Option<PathBuf>,
+
+	/// Specifies the form of the change list
+	#[arg(long, value_enum, default_value_t=ChangeFormat::Shell, requires = "change_list")]
+	change_format: ChangeFormat,
+
+	/// Specifies to hash every file, neither reusing nor updating the checksums cached within the directory for files which have not changed since they were last hashed
+	#[arg(long, conflicts_with = "rebuild_cache")]
+	no_cache: bool,
+
+	/// Specifies to hash every file, replacing the checksums cached within the directory
+	#[arg(long)]
+	rebuild_cache: bool,
+
+	/// Specifies a percentage of the organised files (such as 5%) to read again once organised, verifying that their contents still match the checksums they were named by
+	#[arg(long)]
+	verify_sample: Option<Percentage>,
+
+	/// Specifies the number of threads to organise files with, which defaults to the number of processors
+	#[arg(long)]
+	threads: Option<usize>,
+
+	/// Specifies the most files to read the contents of at once, which defaults to one upon rotational storage (such as hard drives) and otherwise to no limit
+	#[arg(long)]
+	io_concurrency: Option<usize>,
+
+	/// Specifies to keep watching the directory once organised, organising new (or modified) files as they arrive once they have settled, until interrupted
+	#[arg(short, long, conflicts_with_all = ["from_report", "change_list", "verify_sample", "dry_run"])]
+	watch: bool,
+
+	/// Specifies to discover and hash files, reporting what would be renamed and removed (and the space which would be reclaimed) without changing anything
+	#[arg(short = 'n', long)]
+	dry_run: bool,
+
+	/// Specifies the form of the output, which may be events written as lines of JSON (such as each change made and each file which failed, followed by a summary) for other applications to follow
+	#[arg(long, value_enum, default_value_t=Output::Text)]
+	output: Output,
+
+	/// Specifies to write only warnings (such as files which failed to be organised), rather than the progress of organising and each change made
+	#[arg(short, long, global = true, conflicts_with = "verbose")]
+	quiet: bool,
+
+	/// Specifies to write details of each file (such as its checksum) as well as the progress of organising
+	#[arg(short, long, global = true)]
+	verbose: bool,
+}
+
+/// Determines a command to perform instead of organising.
+#[derive(Debug, Subcommand)]
+enum Command {
+	/// Removes content from a store which is no longer referenced by any file
+	Gc {
+		/// Specifies the store to collect garbage from
+		#[arg(short, long)]
+		store: PathBuf,
+	},
+
+	/// Runs every job within a jobs file, each organising a directory with its own options, and reports upon them together
+	RunAll {
+		/// Specifies the jobs file, in which each job is a section headed by its name (such as `[photos]`) followed by its options upon lines of their own (such as `dir = /srv/photos` or `sidecars`)
+		#[arg(short, long)]
+		jobs: PathBuf,
+
+		/// Specifies to run the jobs at the same time, rather than one after another
+		#[arg(long)]
+		parallel: bool,
+	},
+
+	/// Undoes the renames and removals recorded within a journal, restoring the original names of files and the duplicates removed (from the files they duplicated), other than duplicates deleted from remote directories
+	Undo {
+		/// Specifies the journal to undo, which is written within the directory upon every run (or within the current directory, when organising a remote directory)
+		#[arg(short, long)]
+		journal: PathBuf,
+	},
+
+	/// Lists past runs, with how many files each organised and how many duplicates it removed
+	History {
+		/// Specifies the number of the most recent runs to list
+		#[arg(short, long, default_value_t = 20)]
+		limit: usize,
+	},
+
+	/// Mounts a read-only view of a store, presenting its content under original names within year and month folders
+	#[cfg(all(feature = "fuse", any(target_os = "linux", target_os = "macos")))]
+	Mount {
+		/// Specifies the store to present
+		#[arg(short, long)]
+		store: PathBuf,
+
+		/// Specifies the (empty) directory to mount the view onto
+		#[arg(short, long)]
+		mountpoint: PathBuf,
+	},
+}
+
+/// Determines the mode of operation.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Mode {
+	/// Indicates that quick (shallow) comparisons of files based on their name should be performed.
+	Fast,
+
+	/// Indicates that slow (deep) comparisons of files based on their entire contents should be performed.
+	Full,
+}
+
+/// Runs the command line application with the specified arguments, returning the status to exit with.
+pub fn run(args: Args) -> ExitCode {
+	let session = Arc::new(Session::new(args.output, verbosity(&args)));
+
+	match session.run(args.threads, || perform(&args)) {
+		Ok(code) => code,
+		Err(err) => {
+			warning!("Failed to start threads: {}.", err);
+
+			ExitCode::FAILURE
+		}
+	}
+}
+
+/// Returns the verbosity of the messages written with the specified arguments.
+fn verbosity(args: &Args) -> Verbosity {
+	match (args.quiet, args.verbose) {
+		(true, _) => Verbosity::Quiet,
+		(_, true) => Verbosity::Verbose,
+		_ => Verbosity::Normal,
+	}
+}
+
+/// Performs the command (or organises the directory) specified by the specified arguments, within the current session, returning the status to exit with.
+fn perform(args: &Args) -> ExitCode {
+	match &args.command {
+		Some(Command::Gc { store }) => match collect(store) {
+			Ok(()) => say!("Successfully collected store."),
+			Err(err) => {
+				warning!("Failed to collect store: {}.", err);
+
+				return ExitCode::FAILURE;
+			}
+		},
+		#[cfg(all(feature = "fuse", any(target_os = "linux", target_os = "macos")))]
+		Some(Command::Mount { store, mountpoint }) => match mount(store, mountpoint) {
+			Ok(()) => say!("Successfully unmounted store."),
+			Err(err) => {
+				warning!("Failed to mount store: {}.", err);
+
+				return ExitCode::FAILURE;
+			}
+		},
+		Some(Command::RunAll { jobs, parallel }) => {
+			let started = SystemTime::now();
+			let result = run_all(jobs, *parallel);
+
+			if let Err(err) = history::record(started, &result) {
+				warning!("Failed to record run in history: {}.", err);
+			}
+
+			summarise(false, &result);
+
+			match result {
+				Ok(()) => say!("Successfully ran all jobs."),
+				Err(err) => {
+					warning!("Failed to run all jobs: {}.", err);
+
+					return ExitCode::FAILURE;
+				}
+			}
+		}
+		Some(Command::Undo { journal }) => match undo(journal) {
+			Ok(()) => say!("Successfully undid journal."),
+			Err(err) => {
+				warning!("Failed to undo journal: {}.", err);
+
+				return ExitCode::FAILURE;
+			}
+		},
+		Some(Command::History { limit }) => match history(*limit) {
+			Ok(()) => (),
+			Err(err) => {
+				warning!("Failed to list history: {}.", err);
+
+				return ExitCode::FAILURE;
+			}
+		},
+		None => {
+			let started = SystemTime::now();
+			let result = organise(args);
+
+			// The history only serves to review past runs, so failing to record one never fails the run itself, whereas dry runs leave nothing to review.
+
+			if !args.dry_run {
+				if let Err(err) = history::record(started, &result) {
+					warning!("Failed to record run in history: {}.", err);
+				}
+			}
+
+			summarise(args.dry_run, &result);
+
+			match result {
+				Ok(()) if args.dry_run => say!("Successfully previewed organising directory."),
+				Ok(()) => say!("Successfully organised directory."),
+				Err(err) => {
+					warning!("Failed to organise directory: {}.", err);
+
+					return ExitCode::FAILURE;
+				}
+			}
+		}
+	};
+
+	ExitCode::SUCCESS
+}
+
+/// Represents an organise-related error.
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum OrganiseError {
+	/// Indicates that the directory could not be read for its files.
+	FailedToListDirectory(io::Error),
+
+	/// Indicates that a particular file could not be read for its contents.
+	FailedToReadFile(io::Error),
+
+	/// Indicates that a duplicate file could not be removed.
+	FailedToRemoveDuplicateFile(io::Error),
+
+	/// Indicates that a duplicate file could not share its contents with the file which is kept.
+	#[cfg(target_os = "linux")]
+	FailedToDeduplicateFile(io::Error),
+
+	/// Indicates that a new file could not be renamed.
+	FailedToRenameNewFile(io::Error),
+
+	/// Indicates that the directory is protected from being organised (such as a system directory or the home directory) without forcing.
+	RefusedToOrganiseProtected(PathBuf),
+
+	/// Indicates that files could not be written within the directory (such as upon a read-only mount).
+	FailedToWriteDirectory(io::Error),
+
+	/// Indicates that the last modified timestamp on an original duplicate file could not be changed.
+	FailedToSetLastModified(io::Error),
+
+	/// Indicates that some of the files within the directory could not be organised.
+	FailedToOrganiseFiles(usize),
+
+	/// Indicates that the threads to organise files with could not be started.
+	FailedToStartThreads(rayon::ThreadPoolBuildError),
+
+	/// Indicates that the files within some of the folders beneath the directory could not be organised.
+	FailedToOrganiseFolders(usize),
+
+	/// Indicates that a particular image could not be decoded for its pixels.
+	FailedToDecodeImage(image::ImageError),
+
+	/// Indicates that a particular media file could not be probed using an external tool.
+	FailedToProbeMedia(io::Error),
+
+	/// Indicates that a particular archive could not be read for its members.
+	FailedToReadArchive(io::Error),
+
+	/// Indicates that a companion (sidecar) file could not be moved alongside the file it accompanies.
+	FailedToRenameSidecarFile(io::Error),
+
+	/// Indicates that a particular document could not be parsed for its content.
+	FailedToReadDocument(lopdf::Error),
+
+	/// Indicates that a particular audio file could not be read for its tags.
+	FailedToReadTags(lofty::error::FileParseError),
+
+	/// Indicates that the folder into which a file is grouped could not be created.
+	FailedToCreateFolder(io::Error),
+
+	/// Indicates that a checksum file could not be written.
+	FailedToWriteChecksums(io::Error),
+
+	/// Indicates that a change list could not be written.
+	FailedToWriteChanges(io::Error),
+
+	/// Indicates that files did not match the checksums listed for them.
+	FailedToVerifyChecksums(usize),
+
+	/// Indicates that organised files sampled once organising had finished did not match the checksums they were named by.
+	FailedToVerifySample(usize),
+
+	/// Indicates that a jobs file could not be read, or listed invalid jobs.
+	FailedToReadJobs(io::Error),
+
+	/// Indicates that some of the jobs within a jobs file failed.
+	FailedToRunJobs(usize),
+
+	/// Indicates that a journal could not be read, or held invalid entries.
+	FailedToReadJournal(io::Error),
+
+	/// Indicates that some of the operations within a journal could not be undone.
+	FailedToUndoJournal(usize),
+
+	/// Indicates that the directory could not be watched for new files.
+	FailedToWatchDirectory(notify::Error),
+
+	/// Indicates that a .torrent file could not be read.
+	FailedToReadTorrent(io::Error),
+
+	/// Indicates that the content or index of a store could not be updated.
+	FailedToUpdateStore(io::Error),
+
+	/// Indicates that a remote directory could not be accessed.
+	FailedToAccessRemote(io::Error),
+
+	/// Indicates that a view of a store could not be mounted.
+	#[cfg(all(feature = "fuse", any(target_os = "linux", target_os = "macos")))]
+	FailedToMountStore(io::Error),
+}
+
+/// Indicates the result of an organisation operation.
+type OrganiseResult = Result<(), OrganiseError>;
+
+impl Display for OrganiseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::FailedToListDirectory(e) => write!(f, "failed to list files [{}]", e),
+			Self::FailedToReadFile(e) => write!(f, "failed to read file [{}]", e),
+			Self::FailedToRemoveDuplicateFile(e) => write!(f, "failed to remove duplicate file [{}]", e),
+			#[cfg(target_os = "linux")]
+			Self::FailedToDeduplicateFile(e) => write!(f, "failed to deduplicate file [{}]", e),
+			Self::FailedToRenameNewFile(e) => write!(f, "failed to rename new file [{}]", e),
+			Self::FailedToWriteDirectory(e) => write!(f, "failed to write within directory [{}]", e),
+			Self::RefusedToOrganiseProtected(p) => write!(f, "refused to organise protected directory without forcing [{}]", p.display()),
+			Self::FailedToSetLastModified(e) => write!(f, "failed to set last modified time on file [{}]", e),
+			Self::FailedToOrganiseFiles(n) => write!(f, "failed to organise files [{} files failed]", n),
+			Self::FailedToStartThreads(e) => write!(f, "failed to start threads [{}]", e),
+			Self::FailedToOrganiseFolders(n) => write!(f, "failed to organise folders [{} folders failed]", n),
+			Self::FailedToDecodeImage(e) => write!(f, "failed to decode image [{}]", e),
+			Self::FailedToProbeMedia(e) => write!(f, "failed to probe media [{}]", e),
+			Self::FailedToReadArchive(e) => write!(f, "failed to read archive [{}]", e),
+			Self::FailedToRenameSidecarFile(e) => write!(f, "failed to rename sidecar file [{}]", e),
+			Self::FailedToReadDocument(e) => write!(f, "failed to read document [{}]", e),
+			Self::FailedToReadTags(e) => write!(f, "failed to read tags [{}]", e),
+			Self::FailedToCreateFolder(e) => write!(f, "failed to create folder [{}]", e),
+			Self::FailedToWriteChecksums(e) => write!(f, "failed to write checksums [{}]", e),
+			Self::FailedToWriteChanges(e) => write!(f, "failed to write changes [{}]", e),
+			Self::FailedToAccessRemote(e) => write!(f, "failed to access remote directory [{}]", e),
+			Self::FailedToUpdateStore(e) => write!(f, "failed to update store [{}]", e),
+			Self::FailedToReadTorrent(e) => write!(f, "failed to read torrent [{}]", e),
+			Self::FailedToReadJobs(e) => write!(f, "failed to read jobs [{}]", e),
+			Self::FailedToRunJobs(n) => write!(f, "failed to run jobs [{} jobs failed]", n),
+			Self::FailedToWatchDirectory(e) => write!(f, "failed to watch directory [{}]", e),
+			Self::FailedToReadJournal(e) => write!(f, "failed to read journal [{}]", e),
+			Self::FailedToUndoJournal(n) => write!(f, "failed to undo journal [{} operations failed]", n),
+			#[cfg(all(feature = "fuse", any(target_os = "linux", target_os = "macos")))]
+			Self::FailedToMountStore(e) => write!(f, "failed to mount store [{}]", e),
+			Self::FailedToVerifyChecksums(n) => write!(f, "failed to verify checksums [{} files are mismatched or missing]", n),
+			Self::FailedToVerifySample(n) => write!(f, "failed to verify sample [{} files are mismatched or missing]", n),
+		}
+	}
+}
+
+impl Error for OrganiseError {}
+
+/// Organises the directory using the specified arguments.
+fn organise(args: &Args) -> OrganiseResult {
+	// The directory is only ever absent when a command is performed or a report is organised instead, as enforced when parsing.
+
+	if let Some(report) = &args.from_report {
+		return reports::organise(report, args);
+	}
+
+	let dir = args.dir.as_deref().unwrap_or(Path::new("."));
+
+	if let Some(backend) = remote::open(dir, args)? {
+		return remote::organise(backend.as_ref(), args);
+	}
+
+	// Renaming every file within the wrong directory cannot be undone, so directories which are never meant to be organised are refused.
+
+	if let Some(protected) = protection::protected(dir, &args.protect).filter(|_| !args.force) {
+		return Err(OrganiseError::RefusedToOrganiseProtected(protected));
+	}
+
+	if !args.dry_run {
+		probe(dir)?;
+
+		// Every rename and removal is journalled as it is made, so that the run may be undone should it go wrong.
+
+		journal::open(dir);
+	}
+
+	// Reading many files at once from a hard drive makes it seek between them, which is far slower than reading them in turn.
+
+	match args.io_concurrency {
+		Some(limit) => storage::limit(limit),
+		None if storage::rotational(dir) == Some(true) => {
+			say!("Found rotational storage beneath <{}>, reading one file at a time.", dir.display());
+
+			storage::limit(1);
+		}
+		None => (),
+	}
+
+	// Dry runs leave the store as it is, and rename nothing to be verified.
+
+	let store = args.store.as_ref().filter(|_| !args.dry_run).map(Store::open).transpose()?;
+
+	#[cfg(all(target_os = "linux", feature = "selinux"))]
+	let store = store.map(|s| s.labelled(args.selinux));
+
+	// Files which have not changed since they were last hashed reuse their cached checksums, even in full mode.
+
+	let cache = (!args.no_cache).then(|| Cache::open(dir, args.algorithm, args.rebuild_cache));
+	let changes = args.change_list.as_ref().map(|_| ChangeList::new(dir));
+	let sample = args.verify_sample.filter(|_| !args.dry_run).map(|p| Sample::new(p, args.algorithm));
+
+	// Each folder is organised in place, so files are only ever duplicates of others within the same folder.
+
+	let folders = match args.recursive {
+		true => folders(dir, args.max_depth, &[args.store.as_deref(), args.quarantine.as_deref()]),
+		false => vec![dir.to_owned()],
+	};
+
+	let mut failures = 0;
+	let mut failed = 0;
+
+	for folder in &folders {
+		match organise_folder(folder, store.as_ref(), cache.as_ref(), changes.as_ref(), sample.as_ref(), args) {
+			Ok(()) => (),
+			Err(OrganiseError::FailedToOrganiseFiles(n)) => failed += n,
+			Err(e) if args.recursive => {
+				warning!("Failed to organise folder <{}>: {}.", folder.display(), e);
+
+				failures += 1;
+			}
+			Err(e) => return Err(e),
+		}
+	}
+
+	// Checksums are written before the cache, so that those computed only to be written are cached as well.
+
+	if let Some(algorithm) = written_checksums(args).filter(|_| !args.dry_run) {
+		say!("Writing checksums...");
+
+		let path = checksums::write(dir, &checksummed(dir, args), algorithm, cache.as_ref())?;
+
+		say!("Wrote checksums to <{}>.", path.display());
+	}
+
+	// The cache only serves to speed up later runs, so failing to write it never fails the run itself.
+
+	if let Some(cache) = cache.as_ref().filter(|_| !args.dry_run) {
+		match cache.write() {
+			Ok(n) => say!("Cached {} checksums.", n),
+			Err(e) => warning!("Failed to write cache: {}.", e),
+		}
+	}
+
+	if let Some((path, changes)) = args.change_list.as_ref().zip(changes) {
+		changes.write(path, args.change_format).map_err(OrganiseError::FailedToWriteChanges)?;
+
+		say!("Wrote {} changes to <{}>.", changes.len(), path.display());
+	}
+
+	// Verifying is left until everything else is done, so that a failure does not prevent the checksums and changes from being written.
+
+	if let Some(sample) = sample {
+		verify_sample(&sample)?;
+	}
+
+	// Files which fail are reported as they do, leaving the others (and those within other folders) to be organised regardless.
+
+	let result = match (failures, failed) {
+		(0, 0) => Ok(()),
+		(0, n) => Err(OrganiseError::FailedToOrganiseFiles(n)),
+		(n, _) => Err(OrganiseError::FailedToOrganiseFolders(n)),
+	};
+
+	// Watching only ends once interrupted, so files which failed beforehand are reported before it starts, and still fail the run should watching end otherwise.
+
+	match result {
+		Ok(()) if args.watch => watch::watch(dir, store.as_ref(), cache.as_ref(), args),
+		Err(OrganiseError::FailedToOrganiseFiles(n)) if args.watch => {
+			warning!("Failed to organise {} files before watching, watching regardless.", n);
+
+			watch::watch(dir, store.as_ref(), cache.as_ref(), args).and(Err(OrganiseError::FailedToOrganiseFiles(n)))
+		}
+		result => result,
+	}
+}
+
+/// Organises the files within the specified folder (but not those within folders beneath it) using the specified arguments.
+fn organise_folder(dir: &Path, store: Option<&Store>, cache: Option<&Cache>, changes: Option<&ChangeList>, sample: Option<&Sample>, args: &Args) -> OrganiseResult {
+	say!("Discovering files in <{}>...", dir.display());
+
+	let start = Instant::now();
+
+	let files = list(dir)?;
+
+	for (link, kind) in links::find(dir) {
+		say!("Skipping {} <{}>, which is not followed.", kind.noun(), link.display());
+	}
+
+	if args.verify_checksums {
+		verify_checksums(&files)?;
+	}
+
+	// Prior audits are trusted for those files which have not changed since, so they need not be hashed again.
+
+	let audited = match args.algorithm {
+		Hash::Md5 => checksums::audited(&files),
+		_ => HashMap::new(),
+	};
+
+	// Renaming a file being seeded would break seeding, so such files are protected from being organised.
+
+	let seeded = if args.protect_torrents { protect_torrents(dir, &files, args)? } else { HashSet::new() };
+
+	// Companion files are never organised by themselves, but instead follow the file they accompany.
+
+	let sidecars = if args.sidecars { Sidecars::new(&files) } else { Sidecars::default() };
+	let files = files.into_iter().filter(|p| !sidecars.is_sidecar(p) && !checksums::is_checksum_file(p) && !journal::is_journal(p) && !cache::is_index(p) && !seeded.contains(p));
+
+	// Files which are not to be organised are filtered out before any are hashed, so they cost no more than being listed, whereas those whose size cannot be read are left to fail when they are organised.
+
+	let (files, filtered): (Vec<PathBuf>, Vec<PathBuf>) = files.partition(|p| filter::admits(p, p.metadata().ok().map(|m| m.len()), args));
+	let files = files.into_iter();
+
+	if !filtered.is_empty() {
+		for file in &filtered {
+			detail!("Skipping file <{}>, which is not included by the filters.", file.display());
+
+			output::skipped(file, "not included by the filters");
+		}
+
+		say!("Skipping {} files, which are not included by the filters.", filtered.len());
+	}
+
+	// Files modified moments ago may still be being written, so hashing and renaming them is left until a later run.
+
+	let settle = Duration::from_secs(args.settle_time);
+	let files = files.filter(|p| match modified_within(p, settle) {
+		true => {
+			say!("Deferring file <{}>, which was modified within the last {} seconds.", p.display(), args.settle_time);
+
+			output::skipped(p, &format!("modified within the last {} seconds", args.settle_time));
+
+			false
+		}
+		false => true,
+	});
+
+	// Empty files all share the same checksum, so are set aside from the others unless they are to be organised as such (and are not old enough to be removed).
+
+	let stale = args.remove_empty_older_than.map(|d| Duration::from_secs(d * 24 * 60 * 60));
+	let (empty, files): (Vec<PathBuf>, Vec<PathBuf>) = files.partition(|p| p.metadata().is_ok_and(|m| m.len() == 0) && (args.empty_files != EmptyFiles::Organise || stale.is_some_and(|s| !modified_within(p, s))));
+	let files = files.into_iter();
+
+	// Check either every file or only the files where the name does not appear to be a hash.
+
+	#[rustfmt::skip]
+	let files: Vec<PathBuf> = match args.mode {
+		Mode::Full => files.collect(),
+		Mode::Fast => files
+			.filter(|p| match args.annex {
+				true => annex::parse(p).is_none(),
+				false => p.file_stem()
+					.and_then(|n| n.to_str())
+					.map(|n| !args.algorithm.is_checksum(n))
+					.unwrap_or(true),
+			})
+			.collect(),
+	};
+
+	say!("Discovered {} files in {:#?}.", files.len(), start.elapsed());
+	say!("Organising {} files...", files.len());
+
+	// Files held open by other applications cannot be read (let alone renamed), so they may at least be hashed through a shadow copy, which is only made once such a file is found.
+
+	#[cfg(windows)]
+	let shadows = args.shadow_copy.then(shadow::ShadowCopies::default);
+
+	let mut failures = files
+		.into_par_iter()
+		.filter(|file| match process(file, &sidecars, &audited, store, cache, changes, sample, args) {
+			Ok(()) => false,
+			#[cfg(windows)]
+			Err(OrganiseError::FailedToReadFile(e)) if shadow::locked(&e) && shadows.is_some() => {
+				report_locked(file, shadows.as_ref().unwrap(), args.algorithm);
+
+				false
+			}
+			Err(e) => {
+				fail(file, &e);
+
+				true
+			}
+		})
+		.count();
+
+	if !empty.is_empty() {
+		failures += handle_empty(&empty, stale, changes, args);
+	}
+
+	if args.normalise.contains(&Normalisation::Pixels) {
+		report_encodings(dir)?;
+	}
+
+	if !args.similar.is_empty() {
+		report_similar(dir, &args.similar)?;
+	}
+
+	if args.report_archived {
+		report_archived(dir)?;
+	}
+
+	match failures {
+		0 => Ok(()),
+		n => Err(OrganiseError::FailedToOrganiseFiles(n)),
+	}
+}
+
+/// Removes content from the specified store which is no longer referenced by any file.
+fn collect<T>(store: T) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	say!("Collecting unreferenced content in <{}>...", store.as_ref().display());
+
+	let collection = Store::open(store)?.collect()?;
+
+	say!("Removed {} unreferenced objects totalling {} bytes, keeping {} references.", collection.removed.len(), collection.removed.iter().map(|(_, s)| s).sum::<u64>(), collection.references);
+
+	Ok(())
+}
+
+/// Runs every job within the specified jobs file, either one after another or at the same time, and reports upon their outcomes together.
+fn run_all<T>(file: T, parallel: bool) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	let jobs = jobs::read(&file)?;
+
+	say!("Running {} jobs from <{}>...", jobs.len(), file.as_ref().display());
+
+	// Each job runs within a session of its own, so that neither its options (such as its threads, output and limit upon files read at once) nor its journal are shared with other jobs, whereas what it organised still counts towards the jobs as a whole.
+
+	let run = |job: &jobs::Job| {
+		say!("Running job <{}>...", job.name);
+
+		let start = Instant::now();
+		let session = Arc::new(Session::new(job.args.output, verbosity(&job.args)));
+		let result = session.run(job.args.threads, || organise(&job.args)).unwrap_or_else(|e| Err(OrganiseError::FailedToStartThreads(e)));
+
+		history::absorb(&session);
+
+		match &result {
+			Ok(()) => say!("Finished job <{}>.", job.name),
+			Err(e) => warning!("Failed to run job <{}>: {}.", job.name, e),
+		}
+
+		(start.elapsed(), result)
+	};
+
+	let outcomes: Vec<_> = match parallel {
+		true => jobs.par_iter().map(run).collect(),
+		false => jobs.iter().map(run).collect(),
+	};
+
+	say!("Ran {} jobs:", jobs.len());
+
+	for (job, (elapsed, result)) in jobs.iter().zip(&outcomes) {
+		match result {
+			Ok(()) => say!("\t<{}> succeeded in {:#?}", job.name, elapsed),
+			Err(e) => say!("\t<{}> failed in {:#?} ({})", job.name, elapsed, e),
+		}
+	}
+
+	match outcomes.iter().filter(|(_, r)| r.is_err()).count() {
+		0 => Ok(()),
+		n => Err(OrganiseError::FailedToRunJobs(n)),
+	}
+}
+
+/// Summarises the files organised and duplicates disposed of during this run (or those which would be, during a dry run), broken down by extension and (where they were spread across several) by folder, along with the files which failed and the specified result of the run.
+fn summarise(dry_run: bool, result: &OrganiseResult) {
+	let total = history::total();
+
+	output::summary(&total, dry_run, result.as_ref().err().map(|e| e.to_string()).as_deref());
+
+	if total.organised + total.duplicates == 0 {
+		return;
+	}
+
+	match dry_run {
+		true => say!("Would organise {} files and {} duplicate files totalling {} bytes.", total.organised, total.duplicates, total.reclaimed),
+		false => say!("Organised {} files and {} duplicate files totalling {} bytes.", total.organised, total.duplicates, total.reclaimed),
+	}
+
+	// Those which reclaimed the most space are listed first, as they are where duplicates are worth avoiding.
+
+	let share = |reclaimed: u64| if total.reclaimed == 0 { 0.0 } else { reclaimed as f64 * 100.0 / total.reclaimed as f64 };
+
+	// Files which only failed have nothing to break down.
+
+	let mut extensions: Vec<_> = history::by_extension().into_iter().filter(|(_, t)| t.organised + t.duplicates > 0).collect();
+
+	extensions.sort_by_key(|(_, t)| std::cmp::Reverse((t.reclaimed, t.duplicates, t.organised)));
+
+	say!("By extension:");
+
+	for (extension, tally) in extensions {
+		let extension = if extension.is_empty() { "(none)".to_owned() } else { format!(".{}", extension) };
+
+		say!("\t{} {} files and {} duplicate files totalling {} bytes ({:.1}%)", extension, tally.organised, tally.duplicates, tally.reclaimed, share(tally.reclaimed));
+	}
+
+	let mut folders: Vec<_> = history::by_folder().into_iter().filter(|(_, t)| t.organised + t.duplicates > 0).collect();
+
+	if folders.len() < 2 {
+		return;
+	}
+
+	folders.sort_by_key(|(_, t)| std::cmp::Reverse((t.reclaimed, t.duplicates, t.organised)));
+
+	say!("By folder:");
+
+	for (folder, tally) in folders {
+		say!("\t<{}> {} files and {} duplicate files totalling {} bytes ({:.1}%)", folder.display(), tally.organised, tally.duplicates, tally.reclaimed, share(tally.reclaimed));
+	}
+}
+
+/// Lists the specified number of the most recent runs, from the earliest to the latest.
+fn history(limit: usize) -> OrganiseResult {
+	let runs = history::runs()?;
+
+	for run in &runs[runs.len().saturating_sub(limit)..] {
+		say!(
+			"Run at {} with <{}> {} in {:#?}, organising {} files and {} duplicate files totalling {} bytes.",
+			run.date(),
+			run.arguments,
+			run.outcome,
+			run.duration,
+			run.organised,
+			run.duplicates,
+			run.reclaimed
+		);
+	}
+
+	say!("Listed {} of {} runs.", runs.len().min(limit), runs.len());
+
+	Ok(())
+}
+
+/// Undoes the renames and removals recorded within the specified journal, restoring removed duplicates from the files they duplicated (which held the same contents).
+fn undo<T>(journal: T) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	say!("Undoing journal <{}>...", journal.as_ref().display());
+
+	let entries = journal::read(&journal)?;
+
+	let mut undone = 0;
+	let mut failures = 0;
+	let mut connections = HashMap::new();
+
+	// Operations are undone in the reverse of the order they were made, so that a file renamed over a duplicate has its name back before the duplicate is restored from it.
+
+	for entry in entries.iter().rev() {
+		// Entries which cannot be read are reported as failures, leaving the others to be undone regardless.
+
+		let entry = match entry {
+			Ok(entry) => entry,
+			Err(e) => {
+				warning!("Failed to read entry of journal <{}>: {}.", journal.as_ref().display(), e);
+
+				failures += 1;
+
+				continue;
+			}
+		};
+
+		// Operations upon remote files are undone through a connection to the directory holding them instead.
+
+		if remote::is_remote(&entry.file) {
+			match remote::undo(entry, &mut connections) {
+				Ok(true) => undone += 1,
+				Ok(false) => (),
+				Err(e) => {
+					warning!("Failed to undo operation upon file <{}>: {}.", entry.file.display(), e);
+
+					failures += 1;
+				}
+			}
+
+			continue;
+		}
+
+		// A file which exists has either been restored already (such as by undoing the journal before) or been created since, so it is never overwritten.
+
+		if entry.file.try_exists().unwrap_or(true) {
+			say!("Skipping file <{}>, which already exists.", entry.file.display());
+
+			continue;
+		}
+
+		let result = match &entry.operation {
+			Operation::Rename(to) => {
+				say!("Renaming file <{}> back to <{}>...", to.display(), entry.file.display());
+
+				fs::rename(to, &entry.file)
+			}
+			Operation::Remove(Some(kept)) => {
+				say!("Restoring duplicate file <{}> from <{}>...", entry.file.display(), kept.display());
+
+				fs::copy(kept, &entry.file).map(|_| ())
+			}
+			Operation::Remove(None) => {
+				say!("Restoring empty file <{}>...", entry.file.display());
+
+				File::options().write(true).create_new(true).open(&entry.file).map(|_| ())
+			}
+		};
+
+		match result {
+			Ok(()) => undone += 1,
+			Err(e) => {
+				warning!("Failed to undo operation upon file <{}>: {}.", entry.file.display(), e);
+
+				failures += 1;
+			}
+		}
+	}
+
+	say!("Undid {} of {} operations.", undone, entries.len());
+
+	match failures {
+		0 => Ok(()),
+		n => Err(OrganiseError::FailedToUndoJournal(n)),
+	}
+}
+
+/// Mounts a read-only view of the specified store onto the specified mount point, until it is unmounted.
+#[cfg(all(feature = "fuse", any(target_os = "linux", target_os = "macos")))]
+fn mount<T, U>(store: T, mountpoint: U) -> OrganiseResult
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	say!("Mounting view of store <{}> at <{}>...", store.as_ref().display(), mountpoint.as_ref().display());
+
+	view::mount(store, mountpoint)
+}
+
+/// Returns the algorithm of the checksum file to be written, should one be written at all, which unless specified is that files are named by (so that the checksums computed to name them are reused).
+fn written_checksums(args: &Args) -> Option<Algorithm> {
+	args.write_checksums.map(|a| a.unwrap_or_else(|| Algorithm::named(args.algorithm)))
+}
+
+/// Returns the checksum (of the specified algorithm) recorded by the name of the specified file, should it be a git-annex key (of the same size as the file) or an organised file.
+fn named_checksum<T>(file: T, algorithm: Hash) -> Option<String>
+where
+	T: AsRef<Path>,
+{
+	if let Some(key) = annex::parse(&file).filter(|_| algorithm == Hash::Md5) {
+		let size = file.as_ref().metadata().ok()?.len();
+
+		return key.size.is_none_or(|s| s == size).then_some(key.checksum);
+	}
+
+	let stem = file.as_ref().file_stem()?.to_str()?;
+
+	algorithm.is_checksum(stem).then(|| stem.to_owned())
+}
+
+/// Ensures that files can be written within the specified directory by creating (and removing) a file within it, so that a directory which cannot be organised (such as a read-only mount) fails at once rather than for every file within it.
+fn probe<T>(dir: T) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	let probe = dir.as_ref().join(format!(".directory-organiser-{}", std::process::id()));
+
+	File::options().write(true).create_new(true).open(&probe).map_err(OrganiseError::FailedToWriteDirectory)?;
+	fs::remove_file(&probe).map_err(OrganiseError::FailedToWriteDirectory)
+}
+
+/// Returns whether or not the specified file was last modified within the specified duration, where files modified in the future (as with a skewed clock) are not.
+fn modified_within<T>(file: T, duration: Duration) -> bool
+where
+	T: AsRef<Path>,
+{
+	let modified = file.as_ref().metadata().and_then(|m| m.modified());
+
+	modified.is_ok_and(|m| SystemTime::now().duration_since(m).is_ok_and(|d| d < duration))
+}
+
+/// Records the specified change within the specified change list (if any), emitting an event for it.
+fn record(changes: Option<&ChangeList>, change: Change) {
+	output::change(&change);
+
+	if let Some(changes) = changes {
+		changes.record(change);
+	}
+}
+
+/// Reports that the specified file failed to be organised with the specified error, counting it against this run.
+fn fail<T>(file: T, error: &OrganiseError)
+where
+	T: AsRef<Path>,
+{
+	warning!("Failed to organise file <{}>: {}.", file.as_ref().display(), error);
+
+	output::failure(&file.as_ref().display().to_string(), &error.to_string());
+	history::failed(file.as_ref());
+}
+
+/// Lists the files (but not the folders) within the specified directory.
+fn list<T>(dir: T) -> Result<Vec<PathBuf>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let entries = fs::read_dir(dir).map_err(OrganiseError::FailedToListDirectory)?.flatten();
+
+	// Links are never followed, as they may lead outside of the directory or back into itself.
+
+	Ok(entries.filter(|d| d.file_type().map(|t| !t.is_dir()).unwrap_or(true) && links::link(d).is_none()).map(|d| d.path()).collect())
+}
+
+/// Lists the folders of the specified directory whose files are listed within its checksum file, which are those organised along with the folders beneath them which organised files are grouped into, excluding the folders the organiser keeps for itself (such as the store).
+fn checksummed<T>(dir: T, args: &Args) -> Vec<PathBuf>
+where
+	T: AsRef<Path>,
+{
+	let grouped = if args.group_by.is_some() { grouping::DEPTH } else { 0 };
+
+	let depth = match args.recursive {
+		true => args.max_depth.map(|d| d + grouped),
+		false => Some(grouped),
+	};
+
+	folders(dir, depth, &[args.store.as_deref(), args.quarantine.as_deref()])
+}
+
+/// Lists the specified directory and the folders beneath it down to the specified depth (if any), excluding the specified folders (such as the store, should it lie within), where any folder which cannot be read is skipped along with those beneath it.
+fn folders<T>(dir: T, depth: Option<usize>, excluded: &[Option<&Path>]) -> Vec<PathBuf>
+where
+	T: AsRef<Path>,
+{
+	let excluded: Vec<PathBuf> = excluded.iter().flatten().filter_map(|e| fs::canonicalize(e).ok()).collect();
+
+	let mut folders = vec![dir.as_ref().to_owned()];
+	let mut pending = vec![(dir.as_ref().to_owned(), 0)];
+
+	while let Some((folder, level)) = pending.pop() {
+		if depth.is_some_and(|d| level >= d) {
+			continue;
+		}
+
+		let entries = match fs::read_dir(&folder) {
+			Ok(entries) => entries,
+			Err(e) => {
+				warning!("Failed to list folder <{}>: {}.", folder.display(), e);
+
+				continue;
+			}
+		};
+
+		// Links are never followed, as they may lead outside of the directory or back into itself.
+
+		for entry in entries.flatten().filter(|d| d.file_type().is_ok_and(|t| t.is_dir()) && links::link(d).is_none()) {
+			if fs::canonicalize(entry.path()).is_ok_and(|p| excluded.contains(&p)) {
+				continue;
+			}
+
+			folders.push(entry.path());
+			pending.push((entry.path(), level + 1));
+		}
+	}
+
+	folders
+}
+
+/// Reports the checksum (computed by the specified algorithm) of the specified file held open by another application, reading it through a shadow copy of its volume and leaving its rename until it is next organised.
+#[cfg(windows)]
+fn report_locked<T>(file: T, shadows: &shadow::ShadowCopies, algorithm: Hash)
+where
+	T: AsRef<Path>,
+{
+	match shadows.path(&file).and_then(|shadow| algorithm.compute(shadow)) {
+		Ok(checksum) => say!("Found locked file <{}> with checksum <{}>, deferring its rename until it is no longer in use.", file.as_ref().display(), checksum),
+		Err(e) => warning!("Failed to read locked file <{}> through shadow copy: {}.", file.as_ref().display(), e),
+	}
+}
+
+/// Removes those of the specified empty files last modified longer ago than the specified duration (if any), and lists the remainder should they be reported, returning the number which failed to be removed.
+fn handle_empty(empty: &[PathBuf], stale: Option<Duration>, changes: Option<&ChangeList>, args: &Args) -> usize {
+	// Empty files have no content to share, so they are deleted rather than deduplicated.
+
+	let disposal = match args.duplicates {
+		#[cfg(target_os = "linux")]
+		Duplicates::Dedupe => Duplicates::Delete,
+		duplicates => duplicates,
+	};
+
+	let mut remaining = Vec::new();
+	let mut failures = 0;
+
+	for file in empty {
+		if stale.is_none_or(|s| modified_within(file, s)) {
+			remaining.push(file);
+
+			continue;
+		}
+
+		if args.dry_run {
+			say!("Would {} empty file <{}>.", disposal.action(), file.display());
+			record(changes, Change::Remove(file.to_owned()));
+
+			continue;
+		}
+
+		say!("{} empty file <{}>...", disposal.verb(), file.display());
+
+		let checksum = args.algorithm.compute(file).unwrap_or_default();
+
+		match disposal.dispose(file, file, args.quarantine.as_deref()) {
+			Ok(()) => {
+				record(changes, Change::Remove(file.to_owned()));
+				journal::removed(file, None, &checksum);
+			}
+			Err(e) => {
+				fail(file, &OrganiseError::FailedToRemoveDuplicateFile(e));
+
+				failures += 1;
+			}
+		}
+	}
+
+	if args.empty_files == EmptyFiles::Report {
+		say!("Found {} empty files:", remaining.len());
+
+		for file in remaining {
+			say!("\t<{}>", file.display());
+		}
+	}
+
+	failures
+}
+
+/// Reports groups of images within the specified directory which share the same pixels but are stored in different formats.
+fn report_encodings<T>(dir: T) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+	for file in list(dir)? {
+		if let (Some(stem), Ok(_)) = (file.file_stem().and_then(|s| s.to_str()), ImageFormat::from_path(&file)) {
+			groups.entry(stem.to_owned()).or_default().push(file);
+		}
+	}
+
+	let groups: Vec<Vec<PathBuf>> = groups.into_values().filter(|g| g.len() > 1).collect();
+
+	for group in &groups {
+		say!("Found {} encodings of the same image:", group.len());
+
+		for file in group {
+			let format = ImageFormat::from_path(file).map(|f| f.extensions_str()[0].to_ascii_uppercase()).unwrap_or_default();
+			let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+
+			say!("\t<{}> ({}, {} bytes)", file.display(), format, size);
+		}
+	}
+
+	say!("Found {} images stored in multiple formats.", groups.len());
+
+	Ok(())
+}
+
+/// Reports groups of files within the specified directory which appear to contain the same content despite differing in bytes.
+fn report_similar<T>(dir: T, kinds: &[Similarity]) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	let files = list(dir)?;
+
+	for &kind in kinds {
+		say!("Comparing {} for similarity...", kind.noun());
+
+		let groups = similarity::find_similar(kind, &files);
+
+		for group in &groups {
+			say!("Found {} similar {}:", group.len(), kind.noun());
+
+			for file in group {
+				match &file.details {
+					Some(details) => say!("\t<{}> ({})", file.path.display(), details),
+					None => say!("\t<{}>", file.path.display()),
+				}
+			}
+		}
+
+		say!("Found {} groups of similar {}.", groups.len(), kind.noun());
+	}
+
+	Ok(())
+}
+
+/// Verifies the specified files against the checksum files (such as SFV, md5sum and sha256sum files) amongst them, reporting those which are mismatched or missing.
+fn verify_checksums(files: &[PathBuf]) -> OrganiseResult {
+	say!("Verifying checksums...");
+
+	let verifications = checksums::verify(files)?;
+	let mut discrepancies = 0;
+
+	for verification in &verifications {
+		match verification.outcome {
+			Outcome::Matched => continue,
+			Outcome::Mismatched => say!("Found mismatched file <{}> listed in <{}>.", verification.file.display(), verification.listing.display()),
+			Outcome::Missing => say!("Found missing file <{}> listed in <{}>.", verification.file.display(), verification.listing.display()),
+		}
+
+		discrepancies += 1;
+	}
+
+	say!("Verified {} files with {} discrepancies.", verifications.len(), discrepancies);
+
+	// Organising would rename the files and so lose track of which were damaged, so stop before doing so.
+
+	match discrepancies {
+		0 => Ok(()),
+		n => Err(OrganiseError::FailedToVerifyChecksums(n)),
+	}
+}
+
+/// Verifies a random sample of the organised files against the checksums they were named by.
+fn verify_sample(sample: &Sample) -> OrganiseResult {
+	say!("Verifying sample of {} organised files...", sample.len());
+
+	let verifications = sample.verify()?;
+	let mut discrepancies = 0;
+
+	for (file, outcome) in &verifications {
+		match outcome {
+			Outcome::Matched => continue,
+			Outcome::Mismatched => say!("Found mismatched organised file <{}>.", file.display()),
+			Outcome::Missing => say!("Found missing organised file <{}>.", file.display()),
+		}
+
+		discrepancies += 1;
+	}
+
+	say!("Verified {} sampled files with {} discrepancies.", verifications.len(), discrepancies);
+
+	match discrepancies {
+		0 => Ok(()),
+		n => Err(OrganiseError::FailedToVerifySample(n)),
+	}
+}
+
+/// Returns the files seeded by the .torrent files amongst the specified files (or within the seeding directory) of the specified directory, which are left in place, and reports how complete each torrent is should they be verified.
+fn protect_torrents(dir: &Path, files: &[PathBuf], args: &Args) -> Result<HashSet<PathBuf>, OrganiseError> {
+	let mut torrents = torrent::find_torrents(files, dir);
+
+	if let Some(seeding_dir) = &args.seeding_dir {
+		torrents.extend(torrent::find_torrents(&list(seeding_dir)?, dir));
+	}
+
+	let seeded = torrent::seeded(&torrents);
+
+	say!("Found {} seeded files across {} torrents.", seeded.len(), torrents.len());
+
+	if args.verify_torrents {
+		say!("Verifying torrents...");
+
+		let verifications: Vec<_> = torrents.par_iter().map(|t| (t, t.verify())).collect();
+
+		for (torrent, verification) in verifications {
+			match verification {
+				Ok(v) if v.matched == v.total => say!("Verified torrent <{}> with all {} pieces complete.", torrent.path.display(), v.total),
+				Ok(v) => say!("Verified torrent <{}> with {} of {} pieces complete and {} files missing.", torrent.path.display(), v.matched, v.total, v.missing.len()),
+				Err(e) => warning!("Failed to verify torrent <{}>: {}.", torrent.path.display(), e),
+			}
+		}
+	}
+
+	Ok(seeded)
+}
+
+/// Reports loose files within the specified directory whose contents already exist within an archive in the same directory.
+fn report_archived<T>(dir: T) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	let files = list(dir)?;
+
+	say!("Indexing archives...");
+
+	let archived = archive::find_archived(&files);
+
+	for file in &archived {
+		say!("Found archived file <{}> as <{}> in <{}> ({} bytes).", file.path.display(), file.member, file.archive.display(), file.size);
+	}
+
+	say!("Found {} archived files totalling {} bytes.", archived.len(), archived.iter().map(|f| f.size).sum::<u64>());
+
+	Ok(())
+}
+
+/// Reports (and counts) what would be done to the specified file of the specified size in organising it under the specified name, were this not a dry run.
+fn preview<T>(file: T, checksum_file: &Path, exists: bool, size: u64, changes: Option<&ChangeList>, args: &Args) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	let file = file.as_ref();
+
+	match exists {
+		true if args.keep.prefers(checksum_file, file, &args.prefer) => {
+			say!("Would replace duplicate file <{}> with <{}>.", checksum_file.display(), file.display());
+
+			record(changes, Change::Replace(file.to_owned(), checksum_file.to_owned()));
+			history::disposed(checksum_file, size);
+		}
+		true => {
+			say!("Would {} duplicate file <{}>.", args.duplicates.action(), file.display());
+
+			record(changes, Change::Remove(file.to_owned()));
+			history::disposed(file, size);
+		}
+		false => {
+			say!("Would rename file <{}> to <{}>.", file.display(), checksum_file.display());
+
+			record(changes, Change::Rename(file.to_owned(), checksum_file.to_owned()));
+			history::organised(file);
+		}
+	}
+
+	Ok(())
+}
+
+/// Attempts to process (organise) the specified file and its companion files using the specified arguments.
+#[allow(clippy::too_many_arguments)]
+fn process<T>(file: T, sidecars: &Sidecars, audited: &HashMap<PathBuf, String>, store: Option<&Store>, cache: Option<&Cache>, changes: Option<&ChangeList>, sample: Option<&Sample>, args: &Args) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	// Clearing quarantine beforehand leaves none to be preserved upon whichever duplicate survives.
+
+	#[cfg(target_os = "macos")]
+	if args.clear_quarantine && !args.dry_run {
+		attributes::clear_quarantine(&file);
+	}
+
+	let before = file.as_ref().metadata().map_err(OrganiseError::FailedToReadFile)?;
+
+	// Names which already record the checksum of their content (such as git-annex keys, or organised files when naming by key) and prior audits are trusted in fast mode, rather than their content being hashed again.
+
+	let named = match (args.mode, args.normalise.is_empty()) {
+		(Mode::Fast, true) => named_checksum(&file, args.algorithm).or_else(|| fs::canonicalize(&file).ok().filter(|_| !audited.is_empty()).and_then(|p| audited.get(&p).cloned())),
+		_ => None,
+	};
+
+	// Only checksums of the contents themselves (rather than of normalised content) can be verified against the contents again.
+
+	let (checksum, verifiable) = match named {
+		Some(checksum) => (checksum, true),
+		None => match normalise::checksum(&file, &args.normalise)? {
+			Some(checksum) => (checksum, false),
+			None => match cache.and_then(|c| c.get(&file, &before)) {
+				Some(checksum) => {
+					detail!("Reusing cached checksum <{}> of file <{}>.", checksum, file.as_ref().display());
+
+					(checksum, true)
+				}
+				None => {
+					let checksum = args.algorithm.compute(&file).map_err(OrganiseError::FailedToReadFile)?;
+
+					detail!("Hashed file <{}> as <{}>.", file.as_ref().display(), checksum);
+
+					(checksum, true)
+				}
+			},
+		},
+	};
+	let sample = sample.filter(|_| verifiable);
+	let cache = cache.filter(|_| verifiable);
+
+	// A file which changed whilst being hashed is still being written, so its checksum already describes stale contents.
+
+	let after = file.as_ref().metadata().map_err(OrganiseError::FailedToReadFile)?;
+
+	if after.len() != before.len() || after.modified().ok() != before.modified().ok() {
+		say!("Deferring file <{}>, which changed whilst being hashed.", file.as_ref().display());
+
+		output::skipped(file.as_ref(), "changed whilst being hashed");
+
+		return Ok(());
+	}
+
+	// Files whose metadata cannot be read are left where they are rather than failing, as they are still organised by their contents.
+
+	let folder = match args.group_by.map(|g| grouping::folder(g, &file)) {
+		Some(Ok(folder)) => folder,
+		Some(Err(e)) => {
+			warning!("Failed to group file <{}>, leaving it in place: {}.", file.as_ref().display(), e);
+
+			None
+		}
+		None => None,
+	};
+
+	// Files are grouped into folders beneath the directory itself rather than beneath the folder holding them, so that those already grouped are left where they are rather than grouped again beneath themselves, whereas files listed within a report may lie anywhere, so are grouped alongside themselves.
+
+	let root = args.dir.as_deref().filter(|d| file.as_ref().starts_with(d)).or(file.as_ref().parent()).unwrap_or(Path::new("."));
+	let folder = folder.map(|f| root.join(grouping::resolve(root, &f)));
+
+	let checksum_file = {
+		let name = match args.annex {
+			true => annex::key(&checksum, file.as_ref().metadata().map_err(OrganiseError::FailedToReadFile)?.len(), file.as_ref().extension().and_then(|e| e.to_str())),
+			false => checksum.clone(),
+		};
+		let base = match &folder {
+			Some(folder) => folder.join(name),
+			None => file.as_ref().with_file_name(name),
+		};
+		let full = file.as_ref().extension().filter(|_| !args.annex).map(|e| base.with_extension(e)).unwrap_or(base);
+
+		full
+	};
+
+	// Files which are already organised may still need to be moved into the store.
+
+	if checksum_file == file.as_ref() || (checksum_file.file_name() == file.as_ref().file_name() && paths::folds(&checksum_file, &file)) {
+		if let Some(cache) = cache {
+			cache.record(&checksum_file, &checksum);
+		}
+
+		return store.map_or(Ok(()), |s| s.adopt(&checksum_file, None));
+	}
+
+	// A name differing from that of the file only by case is the file itself upon case-insensitive file systems, which is renamed rather than mistaken for a duplicate of itself.
+
+	let exists = checksum_file.try_exists().map_err(OrganiseError::FailedToReadFile)? && !paths::folds(&checksum_file, &file);
+
+	// Nothing is renamed during a dry run, so a name is instead taken by the first file which would have been renamed to it.
+
+	let exists = exists || (args.dry_run && !plan::claim(&checksum_file));
+
+	if exists {
+		let size = |f: &Path| f.metadata().ok().map(|m| m.len());
+
+		output::duplicate_group(Some(&checksum), &[(checksum_file.display().to_string(), size(&checksum_file)), (file.as_ref().display().to_string(), size(file.as_ref()))]);
+	}
+
+	if args.dry_run {
+		return preview(&file, &checksum_file, exists, before.len(), changes, args);
+	}
+
+	if let Some(parent) = checksum_file.parent().filter(|_| folder.is_some()) {
+		fs::create_dir_all(parent).map_err(OrganiseError::FailedToCreateFolder)?;
+	}
+
+	// Deduplicated files remain where they are alongside the existing one, so neither they nor their companions are touched otherwise.
+
+	#[cfg(target_os = "linux")]
+	if exists && args.duplicates == Duplicates::Dedupe {
+		say!("{} duplicate file <{}>...", args.duplicates.verb(), file.as_ref().display());
+
+		args.duplicates.dispose(&file, &checksum_file, None).map_err(OrganiseError::FailedToDeduplicateFile)?;
+
+		history::disposed(file.as_ref(), before.len());
+
+		if let Some(cache) = cache {
+			cache.record(&file, &checksum);
+		}
+
+		return Ok(());
+	}
+
+	let companions = sidecars.of(&file);
+	let replaces = exists && args.keep.prefers(&checksum_file, &file, &args.prefer);
+
+	if replaces {
+		say!("Replacing duplicate file <{}> with <{}>...", checksum_file.display(), file.as_ref().display());
+
+		let time = checksum_file.metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::now());
+
+		attributes::preserve(&checksum_file, &file);
+
+		// Deleting permanently is left to the rename itself, whereas other dispositions must happen beforehand.
+
+		if args.duplicates != Duplicates::Delete {
+			args.duplicates.dispose(&checksum_file, &file, args.quarantine.as_deref()).map_err(OrganiseError::FailedToRemoveDuplicateFile)?;
+		}
+
+		journal::removed(&checksum_file, Some(file.as_ref()), &checksum);
+
+		fs::rename(&file, &checksum_file).map_err(OrganiseError::FailedToRenameNewFile)?;
+
+		record(changes, Change::Replace(file.as_ref().to_owned(), checksum_file.to_owned()));
+		journal::renamed(&file, &checksum_file, &checksum);
+		history::disposed(&checksum_file, before.len());
+
+		if let Some(sample) = sample {
+			sample.record(checksum_file.to_owned(), checksum.clone());
+		}
+
+		store::unsealed(&checksum_file, || File::options().write(true).open(&checksum_file).and_then(|f| f.set_modified(time))).map_err(OrganiseError::FailedToSetLastModified)?;
+
+		if let Some(cache) = cache {
+			cache.record(&checksum_file, &checksum);
+		}
+	} else if exists {
+		say!("{} duplicate file <{}>...", args.duplicates.verb(), file.as_ref().display());
+
+		let time = file.as_ref().metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::now());
+
+		// The existing file may be linked to content within the store, which is read-only, so it is only writable whilst its attributes and time are kept, which happens before the duplicate is disposed of so that a failure leaves both in place.
+
+		store::unsealed(&checksum_file, || {
+			attributes::preserve(&file, &checksum_file);
+
+			File::options().write(true).open(&checksum_file).and_then(|f| f.set_modified(time))
+		})
+		.map_err(OrganiseError::FailedToSetLastModified)?;
+
+		args.duplicates.dispose(&file, &checksum_file, args.quarantine.as_deref()).map_err(OrganiseError::FailedToRemoveDuplicateFile)?;
+
+		record(changes, Change::Remove(file.as_ref().to_owned()));
+		journal::removed(&file, Some(&checksum_file), &checksum);
+		history::disposed(file.as_ref(), before.len());
+
+		if let Some(cache) = cache {
+			cache.record(&checksum_file, &checksum);
+		}
+	} else {
+		say!("Organising new file <{}>...", file.as_ref().display());
+
+		fs::rename(&file, &checksum_file).map_err(OrganiseError::FailedToRenameNewFile)?;
+
+		// The file and its companions are moved as a unit, so the file is restored should any companion fail to follow it.
+
+		let followed = sidecar::follow(companions, &checksum_file).inspect_err(|_| {
+			let _ = fs::rename(&checksum_file, &file);
+		})?;
+
+		record(changes, Change::Rename(file.as_ref().to_owned(), checksum_file.to_owned()));
+		journal::renamed(&file, &checksum_file, &checksum);
+		history::organised(file.as_ref());
+
+		for (sidecar, target) in followed {
+			journal::renamed(&sidecar, &target, "");
+			record(changes, Change::Rename(sidecar, target));
+		}
+
+		if let Some(sample) = sample {
+			sample.record(checksum_file.to_owned(), checksum.clone());
+		}
+
+		if let Some(cache) = cache {
+			cache.record(&checksum_file, &checksum);
+		}
+
+		return store.map_or(Ok(()), |s| s.adopt(&checksum_file, Some(file.as_ref())));
+	}
+
+	for (sidecar, target) in sidecar::follow(companions, &checksum_file)? {
+		journal::renamed(&sidecar, &target, "");
+		record(changes, Change::Rename(sidecar, target));
+	}
+
+	// Only a file which replaced the existing one lends it its name, whereas a removed duplicate leaves the existing one as it was.
+
+	store.map_or(Ok(()), |s| s.adopt(&checksum_file, replaces.then_some(file.as_ref())))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::scratch;
+
+	/// Organises the specified directory with the specified arguments (alongside the defaults of the command line), within a session of its own.
+	fn organise_with(dir: &Path, arguments: &[&str]) -> OrganiseResult {
+		let args = Args::parse_from([env!("CARGO_PKG_NAME"), "--dir", dir.to_str().unwrap(), "--settle-time", "0"].iter().chain(arguments));
+		let session = Arc::new(Session::new(Output::Text, Verbosity::Silent));
+
+		session.run(Some(1), || organise(&args)).unwrap()
+	}
+
+	#[test]
+	fn organises_duplicates_into_store() {
+		let (dir, store) = (scratch("store-duplicates"), scratch("store-duplicates-store"));
+		let organised = dir.join(format!("{:x}.txt", md5::compute("same")));
+
+		fs::write(dir.join("a.txt"), "same").unwrap();
+		fs::write(dir.join("b.txt"), "same").unwrap();
+
+		organise_with(&dir, &["--store", store.to_str().unwrap()]).unwrap();
+
+		// A duplicate found by a later run is linked to content which is already read-only.
+
+		fs::write(dir.join("c.txt"), "same").unwrap();
+
+		organise_with(&dir, &["--store", store.to_str().unwrap()]).unwrap();
+
+		assert!(organised.metadata().unwrap().permissions().readonly());
+		assert_eq!(fs::read_to_string(&organised).unwrap(), "same");
+		assert!(["a.txt", "b.txt", "c.txt"].iter().all(|f| !dir.join(f).exists()));
+
+		fs::remove_dir_all(&dir).unwrap();
+		fs::remove_dir_all(&store).unwrap();
+	}
+
+	#[test]
+	fn undoes_organising() {
+		let dir = scratch("undo");
+		let files = [("a.txt", "one"), ("b\tc\n.txt", "two"), ("d.txt", "one")];
+
+		for (name, contents) in files {
+			fs::write(dir.join(name), contents).unwrap();
+		}
+
+		organise_with(&dir, &["--no-cache"]).unwrap();
+
+		assert!(files.iter().all(|(name, _)| !dir.join(name).exists()));
+
+		let journal = fs::read_dir(&dir).unwrap().flatten().map(|e| e.path()).find(|p| journal::is_journal(p)).unwrap();
+		let session = Arc::new(Session::new(Output::Text, Verbosity::Silent));
+
+		session.run(Some(1), || undo(&journal)).unwrap().unwrap();
+
+		for (name, contents) in files {
+			assert_eq!(fs::read_to_string(dir.join(name)).unwrap(), contents);
+		}
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	/// Writes a WAV recording of the specified (constant) sample to the specified file, tagged with the specified artist and album.
+	fn recording(file: &Path, sample: u8, artist: &str, album: &str) {
+		use lofty::{config::WriteOptions, prelude::*, tag::{Tag, TagType}};
+
+		let data = [sample; 64];
+		let format = [&1u16.to_le_bytes()[..], &1u16.to_le_bytes(), &8000u32.to_le_bytes(), &8000u32.to_le_bytes(), &1u16.to_le_bytes(), &8u16.to_le_bytes()].concat();
+		let chunks = [&b"WAVE"[..], b"fmt ", &16u32.to_le_bytes(), &format, b"data", &64u32.to_le_bytes(), &data].concat();
+
+		fs::write(file, [&b"RIFF"[..], &u32::try_from(chunks.len()).unwrap().to_le_bytes(), &chunks].concat()).unwrap();
+
+		let mut tag = Tag::new(TagType::RiffInfo);
+
+		tag.set_artist(artist.to_owned());
+		tag.set_album(album.to_owned());
+		tag.save_to_path(file, WriteOptions::default()).unwrap();
+	}
+
+	#[test]
+	fn groups_beneath_directory_once() {
+		let dir = scratch("group-once");
+
+		fs::create_dir(dir.join("Holiday")).unwrap();
+
+		recording(&dir.join("a.wav"), 1, "ABBA", "Arrival");
+		recording(&dir.join("Holiday").join("b.wav"), 2, "ABBA", "Arrival");
+		fs::write(dir.join("Holiday").join("c.wav"), "not a recording").unwrap();
+
+		// Organising again (even in full mode) leaves grouped files where they are rather than grouping them beneath themselves.
+
+		for _ in 0..2 {
+			organise_with(&dir, &["--recursive", "--mode", "full", "--group-by", "tags", "--no-cache"]).unwrap();
+		}
+
+		let album = dir.join("ABBA").join("Arrival");
+		let grouped: Vec<PathBuf> = fs::read_dir(&album).unwrap().flatten().map(|e| e.path()).collect();
+
+		assert_eq!(grouped.len(), 2);
+		assert!(grouped.iter().all(|f| f.is_file()));
+		assert!(fs::read_dir(dir.join("Holiday")).unwrap().flatten().any(|e| e.path().extension().is_some_and(|e| e == "wav")));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn writes_checksums_files_are_named_by() {
+		let dir = scratch("write-checksums");
+		let checksum = format!("{:x}", md5::compute("one"));
+
+		fs::write(dir.join("a.txt"), "one").unwrap();
+
+		organise_with(&dir, &["--write-checksums"]).unwrap();
+
+		let listing = fs::read_to_string(dir.join("MD5SUMS")).unwrap();
+
+		assert!(listing.lines().any(|l| l == format!("{}  {}.txt", checksum, checksum)));
+		assert!(!dir.join("SHA256SUMS").exists());
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
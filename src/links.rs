@@ -0,0 +1,82 @@
+use std::{
+	fs::{self, DirEntry},
+	path::{Path, PathBuf},
+};
+
+/// The reparse tag of NTFS junctions (mount points).
+#[cfg(windows)]
+const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xa0000003;
+
+/// The bit of a reparse tag indicating that the reparse point refers to another named entity (such as junctions and symbolic links), as opposed to those which store their content elsewhere (such as cloud and deduplicated files).
+#[cfg(windows)]
+const REPARSE_TAG_NAME_SURROGATE: u32 = 0x20000000;
+
+/// Determines the kind of a link to elsewhere, which is never followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link {
+	/// Indicates a symbolic link (or symbolic link reparse point).
+	Symbolic,
+
+	/// Indicates an NTFS junction (mount point).
+	#[cfg_attr(not(windows), allow(dead_code))]
+	Junction,
+}
+
+impl Link {
+	/// Returns the noun describing this kind of link.
+	pub fn noun(&self) -> &'static str {
+		match self {
+			Self::Symbolic => "symbolic link",
+			Self::Junction => "junction",
+		}
+	}
+}
+
+/// Determines whether the specified directory entry is a link to elsewhere, which might lead outside of the directory or back into itself.
+pub fn link(entry: &DirEntry) -> Option<Link> {
+	#[cfg(windows)]
+	{
+		use std::os::windows::fs::MetadataExt;
+
+		use crate::paths;
+
+		use windows::{
+			core::HSTRING,
+			Win32::Storage::FileSystem::{FindClose, FindFirstFileW, FILE_ATTRIBUTE_REPARSE_POINT, WIN32_FIND_DATAW},
+		};
+
+		let metadata = entry.metadata().ok()?;
+
+		if metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT.0 == 0 {
+			return None;
+		}
+
+		// The reparse tag is only available from the directory listing, and distinguishes junctions from symbolic links.
+
+		let mut data = WIN32_FIND_DATAW::default();
+		let handle = unsafe { FindFirstFileW(&HSTRING::from(paths::verbatim(entry.path()).as_os_str()), &mut data) }.ok()?;
+
+		let _ = unsafe { FindClose(handle) };
+
+		match data.dwReserved0 {
+			IO_REPARSE_TAG_MOUNT_POINT => Some(Link::Junction),
+			tag if tag & REPARSE_TAG_NAME_SURROGATE != 0 => Some(Link::Symbolic),
+			_ => None,
+		}
+	}
+
+	#[cfg(not(windows))]
+	entry.file_type().ok().filter(|t| t.is_symlink()).map(|_| Link::Symbolic)
+}
+
+/// Finds the links to elsewhere within the specified directory.
+pub fn find<T>(dir: T) -> Vec<(PathBuf, Link)>
+where
+	T: AsRef<Path>,
+{
+	let Ok(entries) = fs::read_dir(dir) else {
+		return Vec::new();
+	};
+
+	entries.flatten().filter_map(|e| link(&e).map(|l| (e.path(), l))).collect()
+}
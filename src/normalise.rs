@@ -0,0 +1,408 @@
+use std::{
+	fs::File,
+	io::{self, BufReader, Read},
+	path::Path,
+};
+
+use clap::ValueEnum;
+use image::{
+	codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+	AnimationDecoder, Frame, ImageFormat,
+};
+
+use regex::Regex;
+
+use crate::{archive, storage, OrganiseError};
+
+/// The headers of emails which identify the message itself, as opposed to headers added in transport (such as Received or Delivered-To).
+const MESSAGE_HEADERS: &[&str] = &["cc", "date", "from", "message-id", "subject", "to"];
+
+/// The parts of Office Open XML documents which are rewritten by every save (such as timestamps, revision counts and editing time) without the content changing.
+const VOLATILE_OFFICE_PARTS: &[&str] = &["docProps/app.xml", "docProps/core.xml", "word/settings.xml", "xl/calcChain.xml"];
+
+/// Determines the kind of files which are compared by their normalised content rather than their bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Normalisation {
+	/// Indicates that Office Open XML documents (such as DOCX and XLSX) should be compared by their canonicalised content, ignoring volatile metadata.
+	Office,
+
+	/// Indicates that PDF documents should be compared by the content of their pages, ignoring document information and XMP metadata, unless they hold annotations or form fields.
+	Pdf,
+
+	/// Indicates that emails (.eml) should be compared by their Message-ID and canonicalised content, ignoring transport headers.
+	Email,
+
+	/// Indicates that archives (zip, tar and tar.gz) should be compared by the names and contents of their members, ignoring ordering and timestamps.
+	Archives,
+
+	/// Indicates that JPEG images should be compared by their image data, ignoring metadata such as EXIF, XMP and comments.
+	ImageMetadata,
+
+	/// Indicates that lossless images should be compared by their decoded pixels, regardless of their encoding.
+	Pixels,
+}
+
+/// Computes the normalised checksum of the specified file, if any of the specified normalisations apply to it.
+pub fn checksum<T>(file: T, normalisations: &[Normalisation]) -> Result<Option<String>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	// More specific normalisations take precedence over more general ones (such as documents which are also archives).
+
+	for normalisation in Normalisation::value_variants().iter().filter(|n| normalisations.contains(n)) {
+		let digest = match normalisation {
+			Normalisation::Office => office_checksum(&file)?,
+			Normalisation::Pdf => pdf_checksum(&file)?,
+			Normalisation::Email => email_checksum(&file)?,
+			Normalisation::Archives => archive_checksum(&file)?,
+			Normalisation::ImageMetadata => jpeg_checksum(&file)?,
+			Normalisation::Pixels => pixel_checksum(&file)?,
+		};
+
+		if let Some(digest) = digest {
+			return Ok(Some(format!("{:x}", digest)));
+		}
+	}
+
+	Ok(None)
+}
+
+/// Computes the checksum of the specified archive from the sorted names and contents of its members.
+fn archive_checksum<T>(file: T) -> Result<Option<md5::Digest>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let Some(mut members) = archive::members(file)? else {
+		return Ok(None);
+	};
+
+	members.sort_by(|a, b| a.name.cmp(&b.name));
+
+	let mut context = md5::Context::new();
+
+	for member in &members {
+		context.consume(member.name.as_bytes());
+		context.consume([0]);
+		context.consume(member.digest.0);
+	}
+
+	Ok(Some(context.compute()))
+}
+
+/// Computes the checksum of the specified Office Open XML document from its canonicalised content parts.
+fn office_checksum<T>(file: T) -> Result<Option<md5::Digest>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let Some(mut parts) = archive::zip_contents(file)? else {
+		return Ok(None);
+	};
+
+	if !parts.iter().any(|(name, _)| name == "[Content_Types].xml") {
+		return Ok(None);
+	}
+
+	parts.retain(|(name, _)| !VOLATILE_OFFICE_PARTS.contains(&name.as_str()));
+	parts.sort_by(|a, b| a.0.cmp(&b.0));
+
+	let revisions = Regex::new(r#"\s+w(14)?:(rsid\w*|paraId|textId)="[0-9A-Fa-f]*""#).unwrap();
+	let whitespace = Regex::new(r">\s+<").unwrap();
+
+	let mut context = md5::Context::new();
+
+	for (name, data) in &parts {
+		context.consume(name.as_bytes());
+		context.consume([0]);
+
+		// Revision identifiers within markup are regenerated by each save, so are removed alongside insignificant whitespace.
+
+		if name.ends_with(".xml") || name.ends_with(".rels") {
+			let text = String::from_utf8_lossy(data);
+			let text = revisions.replace_all(&text, "");
+			let text = whitespace.replace_all(&text, "><");
+
+			context.consume(md5::compute(text.as_bytes()).0);
+		} else {
+			context.consume(md5::compute(data).0);
+		}
+	}
+
+	Ok(Some(context.compute()))
+}
+
+/// Computes the checksum of the specified PDF document from the content streams (and the images and forms they draw) of each page, unless it holds annotations or form fields.
+fn pdf_checksum<T>(file: T) -> Result<Option<md5::Digest>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let mut header = Vec::with_capacity(5);
+
+	File::open(&file).and_then(|f| f.take(5).read_to_end(&mut header)).map_err(OrganiseError::FailedToReadFile)?;
+
+	if header != b"%PDF-" {
+		return Ok(None);
+	}
+
+	let document = lopdf::Document::load(file).map_err(OrganiseError::FailedToReadDocument)?;
+	let pages = document.get_pages();
+
+	// Annotations and the values of form fields live outside of the content streams, so a form filled in would otherwise be mistaken for the same form left blank, and such documents are compared by their bytes instead.
+
+	let annotated = pages.values().any(|page| document.get_dictionary(*page).is_ok_and(|p| p.has(b"Annots")));
+	let form = document.catalog().is_ok_and(|c| c.has(b"AcroForm"));
+
+	if annotated || form {
+		return Ok(None);
+	}
+
+	let mut context = md5::Context::new();
+
+	// Metadata lives within the trailer and catalog rather than pages, so only the pages themselves are considered.
+
+	for page in pages.into_values() {
+		context.consume(md5::compute(document.get_page_content(page)).0);
+
+		let (direct, inherited) = document.get_page_resources(page).map_err(OrganiseError::FailedToReadDocument)?;
+		let resources = direct.into_iter().chain(inherited.into_iter().filter_map(|id| document.get_dictionary(id).ok()));
+
+		for resource in resources {
+			let Ok(objects) = document.get_dict_in_dict(resource, b"XObject") else {
+				continue;
+			};
+
+			for (name, object) in objects.iter() {
+				if let Ok(stream) = object.as_reference().and_then(|id| document.get_object(id)).and_then(|o| o.as_stream()) {
+					context.consume(name);
+					context.consume(md5::compute(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone())).0);
+				}
+			}
+		}
+	}
+
+	Ok(Some(context.compute()))
+}
+
+/// Computes the checksum of the specified email from its identifying headers and canonicalised body.
+fn email_checksum<T>(file: T) -> Result<Option<md5::Digest>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	if !file.as_ref().extension().is_some_and(|e| e.eq_ignore_ascii_case("eml")) {
+		return Ok(None);
+	}
+
+	let contents = storage::read(file).map_err(OrganiseError::FailedToReadFile)?;
+	let contents = String::from_utf8_lossy(&contents).replace("\r\n", "\n");
+	let (head, body) = contents.split_once("\n\n").unwrap_or((&contents, ""));
+
+	// Unfold headers spanning multiple lines before picking out those which identify the message.
+
+	let mut headers: Vec<(String, String)> = Vec::new();
+
+	for line in head.lines() {
+		match (line.starts_with([' ', '\t']), headers.last_mut()) {
+			(true, Some((_, value))) => {
+				value.push(' ');
+				value.push_str(line.trim());
+			}
+			_ => {
+				if let Some((name, value)) = line.split_once(':') {
+					headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+				}
+			}
+		}
+	}
+
+	headers.retain(|(name, _)| MESSAGE_HEADERS.contains(&name.as_str()));
+	headers.sort();
+
+	let mut context = md5::Context::new();
+
+	for (name, value) in &headers {
+		context.consume(name.as_bytes());
+		context.consume([0]);
+		context.consume(value.split_whitespace().collect::<Vec<&str>>().join(" ").as_bytes());
+		context.consume([0]);
+	}
+
+	for line in body.trim_end().lines() {
+		context.consume(line.trim_end().as_bytes());
+		context.consume([b'\n']);
+	}
+
+	Ok(Some(context.compute()))
+}
+
+/// Computes the checksum of the specified JPEG image, excluding its application (metadata) and comment segments other than those which affect how it appears.
+fn jpeg_checksum<T>(file: T) -> Result<Option<md5::Digest>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let contents = storage::read(file).map_err(OrganiseError::FailedToReadFile)?;
+
+	if !contents.starts_with(&[0xff, 0xd8]) {
+		return Ok(None);
+	}
+
+	let mut context = md5::Context::new();
+	let mut offset = 2;
+
+	while let [0xff, marker, high, low, ..] = contents[offset..] {
+		// The scan (and therefore the rest of the image data) follows the start of scan marker.
+
+		if marker == 0xda {
+			context.consume(&contents[offset..]);
+
+			return Ok(Some(context.compute()));
+		}
+
+		// The length of each segment includes the two bytes holding it, so any shorter is malformed.
+
+		let length = usize::from(u16::from_be_bytes([high, low]));
+
+		if length < 2 {
+			return Err(OrganiseError::FailedToReadFile(io::ErrorKind::InvalidData.into()));
+		}
+
+		let end = offset + 2 + length;
+		let segment = contents.get(offset..end).ok_or_else(|| OrganiseError::FailedToReadFile(io::ErrorKind::UnexpectedEof.into()))?;
+
+		// Application segments hold metadata, with the exception of the ICC profile and the Adobe segment which both affect how the image appears.
+
+		let appearance = (marker == 0xe2 && segment.get(4..).is_some_and(|s| s.starts_with(b"ICC_PROFILE\0"))) || marker == 0xee;
+
+		if appearance || !matches!(marker, 0xe0..=0xef | 0xfe) {
+			context.consume(segment);
+		}
+
+		offset = end;
+	}
+
+	Ok(None)
+}
+
+/// Computes the checksum of the specified image from its dimensions and decoded pixels, along with those of every frame (and how long each is shown for) of an animation.
+fn pixel_checksum<T>(file: T) -> Result<Option<md5::Digest>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let Some(format) = ImageFormat::from_path(&file).ok().filter(|f| f.reading_enabled()) else {
+		return Ok(None);
+	};
+
+	let reader = || File::open(&file).map(BufReader::new).map_err(OrganiseError::FailedToReadFile);
+
+	// Opening an image only decodes its first frame, so animations are decoded frame by frame instead, whereas the pages of a TIFF beyond the first cannot be decoded at all and such images are compared by their bytes.
+
+	let frames = match format {
+		ImageFormat::Gif => Some(GifDecoder::new(reader()?).and_then(|d| d.into_frames().collect_frames())),
+		ImageFormat::Png => {
+			let decoder = PngDecoder::new(reader()?).map_err(OrganiseError::FailedToDecodeImage)?;
+
+			match decoder.is_apng().map_err(OrganiseError::FailedToDecodeImage)? {
+				true => Some(decoder.apng().and_then(|d| d.into_frames().collect_frames())),
+				false => None,
+			}
+		}
+		ImageFormat::WebP => {
+			let decoder = WebPDecoder::new(reader()?).map_err(OrganiseError::FailedToDecodeImage)?;
+
+			match decoder.has_animation() {
+				true => Some(decoder.into_frames().collect_frames()),
+				false => None,
+			}
+		}
+		ImageFormat::Tiff if tiff::decoder::Decoder::new(reader()?).is_ok_and(|d| d.more_images()) => return Ok(None),
+		_ => None,
+	};
+
+	let frames = match frames {
+		Some(frames) => frames.map_err(OrganiseError::FailedToDecodeImage)?,
+		None => vec![Frame::new(image::open(file).map_err(OrganiseError::FailedToDecodeImage)?.into_rgba8())],
+	};
+
+	let mut context = md5::Context::new();
+
+	for frame in &frames {
+		let image = frame.buffer();
+
+		context.consume(image.width().to_le_bytes());
+		context.consume(image.height().to_le_bytes());
+		context.consume(image.as_raw());
+
+		// Still images are hashed alone, so that they match the same image encoded in any format.
+
+		if frames.len() > 1 {
+			let (numerator, denominator) = frame.delay().numer_denom_ms();
+
+			context.consume(numerator.to_le_bytes());
+			context.consume(denominator.to_le_bytes());
+		}
+	}
+
+	Ok(Some(context.compute()))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use super::*;
+	use crate::testing::scratch;
+
+	/// The start of a JPEG image, followed by an application segment holding the specified bytes.
+	fn jpeg(marker: u8, data: &[u8]) -> Vec<u8> {
+		let length = u16::try_from(data.len() + 2).unwrap().to_be_bytes();
+
+		[&[0xff, 0xd8, 0xff, marker], &length[..], data, &[0xff, 0xda, 0x00, 0x02, 0x12, 0x34]].concat()
+	}
+
+	/// Computes the normalised checksum of the specified bytes as a JPEG image.
+	fn checksum(name: &str, contents: &[u8]) -> Result<Option<md5::Digest>, OrganiseError> {
+		let dir = scratch(name);
+		let file = dir.join("image.jpg");
+
+		fs::write(&file, contents).unwrap();
+
+		let checksum = jpeg_checksum(&file);
+
+		fs::remove_dir_all(&dir).unwrap();
+
+		checksum
+	}
+
+	#[test]
+	fn jpeg_ignores_metadata() {
+		let a = checksum("jpeg-metadata-a", &jpeg(0xe1, b"Exif\0\0a")).unwrap();
+		let b = checksum("jpeg-metadata-b", &jpeg(0xe1, b"Exif\0\0b")).unwrap();
+
+		assert!(a.is_some());
+		assert_eq!(a, b);
+	}
+
+	#[test]
+	fn jpeg_keeps_colour_profile() {
+		let a = checksum("jpeg-profile-a", &jpeg(0xe2, b"ICC_PROFILE\0a")).unwrap();
+		let b = checksum("jpeg-profile-b", &jpeg(0xe2, b"ICC_PROFILE\0b")).unwrap();
+
+		assert_ne!(a, b);
+	}
+
+	#[test]
+	fn jpeg_rejects_truncated_segment() {
+		let mut contents = jpeg(0xe1, b"Exif\0\0");
+
+		contents.truncate(8);
+
+		assert!(matches!(checksum("jpeg-truncated", &contents), Err(OrganiseError::FailedToReadFile(_))));
+	}
+
+	#[test]
+	fn jpeg_rejects_short_segment_length() {
+		let short = [0xff, 0xd8, 0xff, 0xe2, 0x00, 0x01, 0xff, 0xda, 0x00, 0x02];
+		let empty = [0xff, 0xd8, 0xff, 0xe2, 0x00, 0x02, 0xff, 0xda, 0x00, 0x02];
+
+		assert!(matches!(checksum("jpeg-short", &short), Err(OrganiseError::FailedToReadFile(_))));
+		assert!(checksum("jpeg-empty", &empty).unwrap().is_some());
+	}
+}
@@ -0,0 +1,180 @@
+use std::{
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+
+use clap::Parser;
+
+use crate::{
+	changes::Change,
+	filter::{Glob, Size},
+	hashing::Hash,
+	output::{Event, Listener, Output, Verbosity},
+	session::Session,
+	Args, Mode, OrganiseError,
+};
+
+/// Represents what was done whilst organising a directory (or, during a dry run, what would be).
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+	/// The files renamed, from their original paths to their organised ones.
+	pub renamed: Vec<(PathBuf, PathBuf)>,
+
+	/// The duplicate files removed, whether they were deleted, trashed or quarantined.
+	pub deleted: Vec<PathBuf>,
+
+	/// The files left alone, along with why they were.
+	pub skipped: Vec<(PathBuf, String)>,
+
+	/// The files which failed to be organised, along with the errors they failed with.
+	pub errors: Vec<(PathBuf, String)>,
+
+	/// The warnings given whilst organising, such as of folders which failed to be organised.
+	pub warnings: Vec<String>,
+}
+
+impl Report {
+	/// Records the specified event within this report.
+	fn record(&mut self, event: &Event) {
+		match event.clone() {
+			Event::Changed(Change::Rename(from, to)) => self.renamed.push((from, to)),
+			Event::Changed(Change::Replace(from, to)) => {
+				self.deleted.push(to.clone());
+				self.renamed.push((from, to));
+			}
+			Event::Changed(Change::Remove(file)) => self.deleted.push(file),
+			Event::Skipped(file, reason) => self.skipped.push((file, reason)),
+			Event::Failed(file, error) => self.errors.push((file, error)),
+			Event::Warned(warning) => self.warnings.push(warning),
+		}
+	}
+}
+
+/// Represents an organiser of a particular directory, for applications which embed organising rather than running it from the command line.
+///
+/// An organiser begins with the same defaults as the command line, which may be changed before organising. Each directory is organised apart from any other organised at the same time, and nothing is written, with warnings given as events instead.
+pub struct Organiser {
+	/// The arguments which the directory is organised with.
+	args: Args,
+
+	/// The callback given every event as it happens, should there be one.
+	progress: Option<Listener>,
+}
+
+impl Organiser {
+	/// Creates an organiser of the specified directory, with the same defaults as the command line.
+	pub fn new<T>(dir: T) -> Self
+	where
+		T: AsRef<Path>,
+	{
+		// Only the defaults are parsed, from arguments which are always valid, whereas the directory is given directly, as parsing it would exit the process should it be rejected (such as one beginning with a dash, or an empty path).
+
+		let mut args = Args::try_parse_from([env!("CARGO_PKG_NAME"), "--dir", "."]).expect("the defaults of the command line are valid");
+
+		args.dir = Some(dir.as_ref().to_owned());
+
+		Self { args, progress: None }
+	}
+
+	/// Specifies the organisation mode.
+	pub fn mode(mut self, mode: Mode) -> Self {
+		self.args.mode = mode;
+		self
+	}
+
+	/// Specifies the algorithm whose checksums files are named by.
+	pub fn algorithm(mut self, algorithm: Hash) -> Self {
+		self.args.algorithm = algorithm;
+		self
+	}
+
+	/// Specifies a pattern matching the names of the only files to organise, alongside any others already specified.
+	pub fn include(mut self, pattern: Glob) -> Self {
+		self.args.include.push(pattern);
+		self
+	}
+
+	/// Specifies a pattern matching the names of files to leave alone, alongside any others already specified.
+	pub fn exclude(mut self, pattern: Glob) -> Self {
+		self.args.exclude.push(pattern);
+		self
+	}
+
+	/// Specifies the size of the smallest files to organise.
+	pub fn min_size(mut self, size: Size) -> Self {
+		self.args.min_size = Some(size);
+		self
+	}
+
+	/// Specifies the size of the largest files to organise.
+	pub fn max_size(mut self, size: Size) -> Self {
+		self.args.max_size = Some(size);
+		self
+	}
+
+	/// Specifies whether or not to organise the files within every folder beneath the directory as well.
+	pub fn recursive(mut self, recursive: bool) -> Self {
+		self.args.recursive = recursive;
+		self
+	}
+
+	/// Specifies the number of seconds since a file was last modified before it is organised.
+	pub fn settle_time(mut self, seconds: u64) -> Self {
+		self.args.settle_time = seconds;
+		self
+	}
+
+	/// Specifies whether or not to only report what would be done, without changing anything.
+	pub fn dry_run(mut self, dry_run: bool) -> Self {
+		self.args.dry_run = dry_run;
+		self
+	}
+
+	/// Specifies a callback to give every event as it happens (such as each file renamed), from whichever thread it happens upon.
+	pub fn on_progress<F>(mut self, progress: F) -> Self
+	where
+		F: Fn(&Event) + Send + Sync + 'static,
+	{
+		self.progress = Some(Arc::new(progress));
+		self
+	}
+
+	/// Organises the directory, returning a report of what was done, where files (and folders) which fail are reported rather than failing the directory as a whole.
+	pub fn organise(&self) -> Result<Report, OrganiseError> {
+		let report = Arc::new(Mutex::new(Report::default()));
+		let recorded = Arc::clone(&report);
+		let progress = self.progress.clone();
+
+		// Each directory is organised within a session of its own, so that neither names claimed, the journal opened nor events are shared with another run.
+
+		let listener: Listener = Arc::new(move |event| {
+			recorded.lock().unwrap().record(event);
+
+			if let Some(progress) = &progress {
+				progress(event);
+			}
+		});
+
+		let session = Arc::new(Session::new(Output::Text, Verbosity::Silent).listen(listener));
+		let result = session.run(None, || crate::organise(&self.args)).unwrap_or_else(|e| Err(OrganiseError::FailedToStartThreads(e)));
+
+		let report = report.lock().unwrap().clone();
+
+		match result {
+			Ok(()) | Err(OrganiseError::FailedToOrganiseFiles(_) | OrganiseError::FailedToOrganiseFolders(_)) => Ok(report),
+			Err(e) => Err(e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn new_keeps_any_directory() {
+		for dir in ["-photos", "--help", "", "photos"] {
+			assert_eq!(Organiser::new(dir).args.dir.as_deref(), Some(Path::new(dir)));
+		}
+	}
+}
@@ -0,0 +1,325 @@
+use std::{
+	collections::HashMap,
+	fmt,
+	io::{self, Write},
+	iter::Peekable,
+	path::{Path, PathBuf},
+	str::Chars,
+	sync::Arc,
+};
+
+use clap::ValueEnum;
+
+use crate::{changes::Change, history::Tally, session};
+
+/// Determines the form of the output written whilst organising.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+	/// Indicates messages written for people to read.
+	Text,
+
+	/// Indicates events written as lines of JSON for other applications to follow, with messages written to the standard error stream instead.
+	Json,
+}
+
+/// Determines how many messages are written for people to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+	/// Indicates that nothing is written, not even warnings, which are only given to the listener (should there be one).
+	Silent,
+
+	/// Indicates that only warnings (such as files which failed to be organised) are written.
+	Quiet,
+
+	/// Indicates that the progress of organising and each change made are written as well.
+	Normal,
+
+	/// Indicates that details of each file (such as its checksum) are written as well.
+	Verbose,
+}
+
+/// Determines a particular event whilst organising, as followed by a listener.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+	/// Indicates that the specified change was made to a file (or, during a dry run, would be).
+	Changed(Change),
+
+	/// Indicates that the specified file was left alone for the specified reason.
+	Skipped(PathBuf, String),
+
+	/// Indicates that the specified file failed to be organised with the specified error.
+	Failed(PathBuf, String),
+
+	/// Indicates the specified warning, such as of a folder which failed to be organised.
+	Warned(String),
+}
+
+/// A listener which is given every event as it happens, such as by an embedding application following progress.
+pub type Listener = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// Writes a message for people to read, to the standard output stream unless it is reserved for events.
+macro_rules! say {
+	($($arg:tt)*) => {
+		$crate::output::message($crate::output::Verbosity::Normal, format_args!($($arg)*))
+	};
+}
+
+/// Writes a message giving further detail for people to read, only when verbose.
+macro_rules! detail {
+	($($arg:tt)*) => {
+		$crate::output::message($crate::output::Verbosity::Verbose, format_args!($($arg)*))
+	};
+}
+
+/// Writes a warning (such as of a file which failed to be organised) for people to read to the standard error stream, even when quiet.
+macro_rules! warning {
+	($($arg:tt)*) => {
+		$crate::output::message($crate::output::Verbosity::Quiet, format_args!($($arg)*))
+	};
+}
+
+pub(crate) use {detail, say, warning};
+
+/// Writes the specified message for people to read, should it be written at the verbosity of the current session.
+pub fn message(verbosity: Verbosity, message: fmt::Arguments) {
+	let session = session::current();
+
+	if verbosity == Verbosity::Quiet {
+		notify(Event::Warned(message.to_string()));
+	}
+
+	if verbosity > session.verbosity {
+		return;
+	}
+
+	match (session.output, verbosity) {
+		(Output::Json, _) | (_, Verbosity::Quiet) => eprintln!("{}", message),
+		_ => println!("{}", message),
+	}
+}
+
+/// Gives the specified event to the listener of the current session, should there be one.
+fn notify(event: Event) {
+	if let Some(listener) = &session::current().listener {
+		listener(&event);
+	}
+}
+
+/// Emits an event upon identifying a group of duplicate files (with their sizes, where known) sharing the specified checksum, before any of them are acted upon.
+pub fn duplicate_group(checksum: Option<&str>, members: &[(String, Option<u64>)]) {
+	let members: Vec<String> = members.iter().map(|(path, size)| format!("{{\"path\":{},\"size\":{}}}", quote(path), size.map_or("null".to_owned(), |s| s.to_string()))).collect();
+
+	emit(format!("{{\"event\":\"duplicate_group\",\"checksum\":{},\"members\":[{}]}}", checksum.map_or("null".to_owned(), quote), members.join(",")));
+}
+
+/// Emits an event upon making (or, during a dry run, planning) the specified change to a file.
+pub fn change(change: &Change) {
+	let path = |p: &Path| quote(&p.display().to_string());
+
+	emit(match change {
+		Change::Rename(from, to) => format!("{{\"event\":\"rename\",\"from\":{},\"to\":{}}}", path(from), path(to)),
+		Change::Replace(from, to) => format!("{{\"event\":\"replace\",\"from\":{},\"to\":{}}}", path(from), path(to)),
+		Change::Remove(file) => format!("{{\"event\":\"remove\",\"path\":{}}}", path(file)),
+	});
+
+	notify(Event::Changed(change.clone()));
+}
+
+/// Emits an event upon leaving the specified file alone for the specified reason.
+pub fn skipped(file: &Path, reason: &str) {
+	emit(format!("{{\"event\":\"skip\",\"path\":{},\"reason\":{}}}", quote(&file.display().to_string()), quote(reason)));
+
+	notify(Event::Skipped(file.to_owned(), reason.to_owned()));
+}
+
+/// Emits an event upon the specified file failing to be organised with the specified error.
+pub fn failure(file: &str, error: &str) {
+	emit(format!("{{\"event\":\"error\",\"path\":{},\"error\":{}}}", quote(file), quote(error)));
+
+	notify(Event::Failed(PathBuf::from(file), error.to_owned()));
+}
+
+/// Emits an event summarising the files organised, duplicates disposed of and files which failed during this run (or those which would be, during a dry run), along with the error the run failed with (if any).
+pub fn summary(total: &Tally, dry_run: bool, error: Option<&str>) {
+	emit(format!(
+		"{{\"event\":\"summary\",\"dry_run\":{},\"organised\":{},\"duplicates\":{},\"reclaimed\":{},\"failed\":{},\"error\":{}}}",
+		dry_run,
+		total.organised,
+		total.duplicates,
+		total.reclaimed,
+		total.failed,
+		error.map_or("null".to_owned(), quote)
+	));
+}
+
+/// Writes the specified event on a line of its own, should events be written during the current session at all.
+fn emit(event: String) {
+	if session::current().output != Output::Json {
+		return;
+	}
+
+	// Each event is written (and flushed) whole, so that events from different threads never interleave and are seen as they happen.
+
+	let mut stdout = io::stdout().lock();
+
+	let _ = writeln!(stdout, "{}", event);
+	let _ = stdout.flush();
+}
+
+/// Quotes the specified text as a JSON string.
+pub fn quote(text: &str) -> String {
+	let mut quoted = String::with_capacity(text.len() + 2);
+
+	quoted.push('"');
+
+	for c in text.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+			c => quoted.push(c),
+		}
+	}
+
+	quoted.push('"');
+	quoted
+}
+
+/// Reads the fields of the specified JSON object (such as an event written on a line of its own), whose values are strings, numbers or booleans (as they are written), where fields which are null are left out.
+pub fn fields(text: &str) -> Option<HashMap<String, String>> {
+	let mut chars = text.trim().chars().peekable();
+	let mut fields = HashMap::new();
+
+	if chars.next()? != '{' {
+		return None;
+	}
+
+	loop {
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+		if fields.is_empty() && chars.next_if_eq(&'}').is_some() {
+			break;
+		}
+
+		let name = unquote(&mut chars)?;
+
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+		if chars.next()? != ':' {
+			return None;
+		}
+
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+		let value = match chars.peek()? {
+			'"' => Some(unquote(&mut chars)?),
+			_ => {
+				let mut value = String::new();
+
+				while let Some(c) = chars.next_if(|c| !matches!(c, ',' | '}')) {
+					value.push(c);
+				}
+
+				match value.trim() {
+					"null" => None,
+					value if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')) => Some(value.to_owned()),
+					_ => return None,
+				}
+			}
+		};
+
+		fields.insert(name, value);
+
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+		match chars.next()? {
+			',' => continue,
+			'}' => break,
+			_ => return None,
+		}
+	}
+
+	// Nothing may follow the object itself, as it would otherwise be a line which was written over or cut short.
+
+	chars.next().is_none().then(|| fields.into_iter().filter_map(|(n, v)| v.map(|v| (n, v))).collect())
+}
+
+/// Reads a JSON string (as quoted by this module, or by anything else) from the start of the specified characters.
+fn unquote(chars: &mut Peekable<Chars>) -> Option<String> {
+	let mut text = String::new();
+
+	if chars.next()? != '"' {
+		return None;
+	}
+
+	loop {
+		match chars.next()? {
+			'"' => return Some(text),
+			'\\' => match chars.next()? {
+				'"' => text.push('"'),
+				'\\' => text.push('\\'),
+				'/' => text.push('/'),
+				'b' => text.push('\u{8}'),
+				'f' => text.push('\u{c}'),
+				'n' => text.push('\n'),
+				'r' => text.push('\r'),
+				't' => text.push('\t'),
+				'u' => {
+					let unit = |chars: &mut Peekable<Chars>| u32::from_str_radix(&chars.take(4).collect::<String>(), 16).ok();
+					let high = unit(chars)?;
+
+					// Characters beyond the basic multilingual plane are escaped as a pair of surrogates.
+
+					let code = match high {
+						0xd800..=0xdbff => match (chars.next(), chars.next()) {
+							(Some('\\'), Some('u')) => 0x10000 + ((high - 0xd800) << 10) + (unit(chars)?.checked_sub(0xdc00).filter(|l| *l < 0x400)?),
+							_ => return None,
+						},
+						code => code,
+					};
+
+					text.push(char::from_u32(code)?);
+				}
+				_ => return None,
+			},
+			c => text.push(c),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fields_read_what_is_quoted() {
+		let text = "a\tb\nc \"d\" \\ e \u{1} \u{1f600}";
+		let fields = fields(&format!("{{\"text\":{}, \"count\" : 3,\"empty\":null,\"flag\":true}}", quote(text))).unwrap();
+
+		assert_eq!(fields.get("text").map(String::as_str), Some(text));
+		assert_eq!(fields.get("count").map(String::as_str), Some("3"));
+		assert_eq!(fields.get("flag").map(String::as_str), Some("true"));
+		assert!(!fields.contains_key("empty"));
+	}
+
+	#[test]
+	fn fields_read_escaped_surrogates() {
+		let fields = fields("{\"text\":\"\\ud83d\\ude00\\u00e9\"}").unwrap();
+
+		assert_eq!(fields.get("text").map(String::as_str), Some("\u{1f600}\u{e9}"));
+	}
+
+	#[test]
+	fn fields_reject_invalid_objects() {
+		assert!(fields("{}").is_some_and(|f| f.is_empty()));
+		assert!(fields("{\"text\":\"unterminated}").is_none());
+		assert!(fields("{\"text\":\"a\"} trailing").is_none());
+		assert!(fields("{\"text\":\"a\",}").is_none());
+		assert!(fields("{\"text\":bare words}").is_none());
+		assert!(fields("{\"text\":\"\\ud83d\"}").is_none());
+		assert!(fields("[\"text\"]").is_none());
+	}
+}
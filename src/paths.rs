@@ -0,0 +1,168 @@
+use std::path::{Path, PathBuf};
+
+use unicode_normalization::UnicodeNormalization;
+
+#[cfg(windows)]
+use std::{
+	ffi::OsString,
+	path::{Component, Prefix},
+};
+
+/// The maximum length of a conventional (non-verbatim) path on Windows, including the terminating null character.
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// Converts the specified path into its verbatim form (such as `\\?\UNC\server\share\...` for network paths), which lifts the limit on path length for those Windows APIs which do not do so themselves.
+#[cfg(windows)]
+pub fn verbatim<T>(path: T) -> PathBuf
+where
+	T: AsRef<Path>,
+{
+	let Ok(path) = std::path::absolute(&path) else {
+		return path.as_ref().to_owned();
+	};
+
+	let mut components = path.components();
+
+	let prefix = match components.next() {
+		Some(Component::Prefix(p)) => match p.kind() {
+			Prefix::UNC(server, share) => [r"\\?\UNC\".as_ref(), server, r"\".as_ref(), share].into_iter().collect::<OsString>(),
+			Prefix::Disk(letter) => OsString::from(format!(r"\\?\{}:", char::from(letter))),
+			_ => return path,
+		},
+		_ => return path,
+	};
+
+	PathBuf::from(prefix).join(components.as_path())
+}
+
+/// Converts the specified path from its verbatim form (such as `\\?\UNC\server\share\...`) into its conventional form (such as `\\server\share\...`) where it can be represented as such, for display and for those Windows APIs which do not understand verbatim paths (such as the shell).
+pub fn simplify<T>(path: T) -> PathBuf
+where
+	T: AsRef<Path>,
+{
+	#[cfg(windows)]
+	{
+		let path = path.as_ref();
+		let mut components = path.components();
+
+		let prefix = match components.next() {
+			Some(Component::Prefix(p)) => match p.kind() {
+				Prefix::VerbatimUNC(server, share) => [r"\\".as_ref(), server, r"\".as_ref(), share].into_iter().collect::<OsString>(),
+				Prefix::VerbatimDisk(letter) => OsString::from(format!("{}:", char::from(letter))),
+				_ => return path.to_owned(),
+			},
+			_ => return path.to_owned(),
+		};
+
+		// Verbatim paths may exceed the limit on length, or contain components which conventional paths would strip or interpret (such as trailing dots and spaces).
+
+		let remainder = components.as_path();
+		let representable = remainder.components().all(|c| match c {
+			Component::Normal(name) => name.to_str().is_some_and(|n| !n.ends_with(['.', ' ']) && !n.contains('/')),
+			_ => true,
+		});
+
+		let simplified = PathBuf::from(prefix).join(remainder);
+
+		match representable && simplified.as_os_str().len() < MAX_PATH {
+			true => simplified,
+			false => path.to_owned(),
+		}
+	}
+
+	#[cfg(not(windows))]
+	path.as_ref().to_owned()
+}
+
+/// Returns the specified name (or path) folded into the form compared by case-insensitive file systems, which neither distinguish case nor how accented characters are composed (such as `é` as a single character or as `e` followed by an accent, as HFS+ stores them).
+pub fn fold<T>(name: T) -> String
+where
+	T: AsRef<Path>,
+{
+	name.as_ref().to_string_lossy().nfc().collect::<String>().to_lowercase()
+}
+
+/// Returns whether or not the specified paths differ only by case (or composition) yet refer to the same file, as happens upon case-insensitive file systems (such as FAT, exFAT, NTFS and APFS), where each is reported as existing through the other.
+pub fn folds<T, U>(a: T, b: U) -> bool
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	fold(&a) == fold(&b) && same_file::is_same_file(a, b).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use super::*;
+	use crate::testing::scratch;
+
+	#[test]
+	fn fold_ignores_case() {
+		assert_eq!(fold("ABBA"), fold("Abba"));
+		assert_eq!(fold("Photo.JPG"), fold("photo.jpg"));
+		assert_ne!(fold("ABBA"), fold("Queen"));
+	}
+
+	#[test]
+	fn fold_ignores_composition() {
+		let composed = "Caf\u{e9}.txt";
+		let decomposed = "Cafe\u{301}.txt";
+
+		assert_ne!(composed, decomposed);
+		assert_eq!(fold(composed), fold(decomposed));
+		assert_eq!(fold("CAFE\u{301}.TXT"), fold(composed));
+	}
+
+	#[test]
+	fn folds_the_same_file() {
+		let dir = scratch("folds-same");
+		let file = dir.join("a.txt");
+
+		fs::write(&file, "a").unwrap();
+
+		assert!(folds(&file, &file));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn folds_only_the_same_file() {
+		let dir = scratch("folds-different");
+		let (upper, lower) = (dir.join("ABBA.txt"), dir.join("abba.txt"));
+
+		fs::write(&upper, "a").unwrap();
+
+		// Upon a case-sensitive file system these are two files which collide only once folded, whereas upon a case-insensitive one they are the same file.
+
+		let sensitive = fs::OpenOptions::new().write(true).create_new(true).open(&lower).is_ok();
+
+		assert_eq!(folds(&upper, &lower), !sensitive);
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn folds_nothing_which_does_not_exist() {
+		let dir = scratch("folds-missing");
+
+		assert!(!folds(dir.join("a.txt"), dir.join("A.txt")));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn folds_nothing_named_differently() {
+		let dir = scratch("folds-named");
+		let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+
+		fs::write(&a, "a").unwrap();
+		fs::hard_link(&a, &b).unwrap();
+
+		assert!(!folds(&a, &b));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
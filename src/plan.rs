@@ -0,0 +1,11 @@
+use std::path::Path;
+
+use crate::session;
+
+/// Claims the specified name for a file which would be organised under it, returning whether or not it was free to claim.
+pub fn claim<T>(name: T) -> bool
+where
+	T: AsRef<Path>,
+{
+	session::current().claimed.lock().unwrap().insert(name.as_ref().to_owned())
+}
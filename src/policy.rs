@@ -0,0 +1,74 @@
+use std::{cmp::Ordering, fs::File, io::BufReader, path::Path};
+
+use clap::ValueEnum;
+use regex::Regex;
+
+/// Determines which of two duplicate files survives organisation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Keep {
+	/// Indicates that the file which has already been organised is always kept.
+	Existing,
+
+	/// Indicates that the file with the richest embedded (EXIF) metadata is kept, such as location or captions.
+	Metadata,
+
+	/// Indicates that the file with the shortest path (in folders, and then in length) is kept.
+	Shortest,
+}
+
+/// Determines what happens to empty (zero-byte) files, which all share the same checksum despite rarely being duplicates of one another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmptyFiles {
+	/// Indicates that empty files should be organised like any other, leaving only one of them.
+	Organise,
+
+	/// Indicates that empty files should be left in place.
+	Skip,
+
+	/// Indicates that empty files should be left in place and listed once organising has finished.
+	Report,
+}
+
+impl Keep {
+	/// Returns whether or not the specified candidate file should replace the specified existing (organised) duplicate, where the specified preferred paths take precedence.
+	pub fn prefers<T, U>(&self, existing: T, candidate: U, paths: &[Regex]) -> bool
+	where
+		T: AsRef<Path>,
+		U: AsRef<Path>,
+	{
+		// Files matching earlier paths are preferred over those matching later ones, and those matching any over those matching none.
+
+		let rank = |file: &Path| paths.iter().position(|p| p.is_match(&file.to_string_lossy())).unwrap_or(paths.len());
+
+		match rank(candidate.as_ref()).cmp(&rank(existing.as_ref())) {
+			Ordering::Less => return true,
+			Ordering::Greater => return false,
+			Ordering::Equal => (),
+		}
+
+		match self {
+			Self::Existing => false,
+			Self::Metadata => metadata_richness(candidate) > metadata_richness(existing),
+			Self::Shortest => (candidate.as_ref().components().count(), candidate.as_ref().as_os_str().len()) < (existing.as_ref().components().count(), existing.as_ref().as_os_str().len()),
+		}
+	}
+}
+
+/// Returns a score representing how much embedded (EXIF) metadata the specified file has.
+fn metadata_richness<T>(file: T) -> usize
+where
+	T: AsRef<Path>,
+{
+	let Ok(exif) = File::open(file).map(BufReader::new).map_err(exif::Error::Io).and_then(|mut r| exif::Reader::new().read_from_container(&mut r)) else {
+		return 0;
+	};
+
+	// Location and descriptive fields are considered far more valuable than technical fields.
+
+	exif.fields()
+		.map(|f| match f.tag {
+			exif::Tag::GPSLatitude | exif::Tag::GPSLongitude | exif::Tag::ImageDescription | exif::Tag::UserComment | exif::Tag::Artist | exif::Tag::Copyright => 10,
+			_ => 1,
+		})
+		.sum()
+}
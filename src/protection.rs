@@ -0,0 +1,148 @@
+use std::{
+	env, fs,
+	path::{Path, PathBuf},
+};
+
+use crate::paths;
+
+/// The environment variable listing additional protected directories, separated as within `PATH`.
+const PROTECTED_VARIABLE: &str = "DIRECTORY_ORGANISER_PROTECT";
+
+/// The directories which are protected themselves, but not the directories beneath them (such as removable media mounted beneath /run/media by udisks2).
+#[cfg(unix)]
+const PROTECTED_ROOTS: &[&str] = &["/home", "/Users", "/Volumes", "/mnt", "/media", "/run", "/run/media", "/srv", "/tmp"];
+
+/// The directories which are protected along with every directory beneath them.
+#[cfg(all(unix, not(target_os = "macos")))]
+const PROTECTED_TREES: &[&str] = &["/bin", "/boot", "/dev", "/etc", "/lib", "/lib32", "/lib64", "/opt", "/proc", "/sbin", "/snap", "/sys", "/usr", "/var"];
+
+/// The directories which are protected along with every directory beneath them.
+#[cfg(target_os = "macos")]
+const PROTECTED_TREES: &[&str] = &["/Applications", "/Library", "/System", "/bin", "/cores", "/dev", "/etc", "/opt", "/private", "/sbin", "/usr", "/var"];
+
+/// The directories holding temporary files (and writable by every user), which are protected themselves but not the directories beneath them, even though they lie within protected directories.
+#[cfg(all(unix, not(target_os = "macos")))]
+const TEMPORARY_TREES: &[&str] = &["/tmp", "/var/tmp"];
+
+/// The directories holding temporary files (and writable by every user), which are protected themselves but not the directories beneath them, even though they lie within protected directories (as /tmp does, leading to /private/tmp).
+#[cfg(target_os = "macos")]
+const TEMPORARY_TREES: &[&str] = &["/private/tmp", "/private/var/tmp", "/private/var/folders"];
+
+/// The directories holding temporary files (and writable by every user), which are protected themselves but not the directories beneath them, even though they lie within protected directories.
+#[cfg(windows)]
+const TEMPORARY_TREES: &[&str] = &[r"C:\Windows\Temp"];
+
+/// The directories which are protected themselves, but not the directories beneath them.
+#[cfg(windows)]
+const PROTECTED_ROOTS: &[&str] = &[r"C:\Users"];
+
+/// The directories which are protected along with every directory beneath them.
+#[cfg(windows)]
+const PROTECTED_TREES: &[&str] = &[r"C:\Windows", r"C:\Program Files", r"C:\Program Files (x86)", r"C:\ProgramData"];
+
+/// Returns the protected directory which the specified directory is, or resides beneath, should it be protected from being organised.
+///
+/// The root of every file system and the home directory (and its application folders) are protected alongside the built-in directories, as are the specified directories and those listed within the environment, which are all protected along with every directory beneath them.
+///
+/// Directories beneath temporary directories (such as /var/tmp, or /tmp upon macOS where it leads to /private/tmp) may still be organised, unless they are specified or listed within the environment.
+pub fn protected<T>(dir: T, extra: &[PathBuf]) -> Option<PathBuf>
+where
+	T: AsRef<Path>,
+{
+	let dir = fs::canonicalize(dir).map(paths::simplify).ok()?;
+	let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from);
+
+	// The root of every file system (or drive) is protected, not only that of the system.
+
+	if dir.parent().is_none() {
+		return Some(dir);
+	}
+
+	let mut roots: Vec<PathBuf> = PROTECTED_ROOTS.iter().map(PathBuf::from).collect();
+	let mut trees: Vec<PathBuf> = PROTECTED_TREES.iter().map(PathBuf::from).collect();
+
+	// The temporary directory of the user (such as beneath /var/folders upon macOS or within AppData upon Windows) holds temporary files as well.
+
+	let temporaries: Vec<PathBuf> = TEMPORARY_TREES.iter().map(PathBuf::from).chain([env::temp_dir()]).collect();
+
+	if let Some(home) = home {
+		roots.push(home.clone());
+
+		#[cfg(target_os = "macos")]
+		trees.extend([home.join("Applications"), home.join("Library")]);
+
+		#[cfg(windows)]
+		trees.push(home.join("AppData"));
+
+		#[cfg(all(unix, not(target_os = "macos")))]
+		trees.extend([home.join(".config"), home.join(".local")]);
+	}
+
+	let mut specified: Vec<PathBuf> = extra.to_vec();
+
+	specified.extend(env::var_os(PROTECTED_VARIABLE).map(|v| env::split_paths(&v).collect::<Vec<_>>()).unwrap_or_default());
+
+	// Protected directories which do not exist cannot be organised anyway, and those which are linked elsewhere (such as /bin upon merged systems) are protected where they lead.
+
+	let canonical = |p: &PathBuf| fs::canonicalize(p).map(paths::simplify).ok();
+
+	// Directories beneath temporary directories are never protected by the built-in directories, whereas those specified protect them all the same.
+
+	let temporary = temporaries.iter().filter_map(canonical).any(|t| dir.starts_with(&t) && dir != t);
+
+	roots
+		.iter()
+		.chain(&temporaries)
+		.filter_map(canonical)
+		.find(|r| *r == dir)
+		.or_else(|| trees.iter().filter(|_| !temporary).chain(&specified).filter_map(canonical).find(|t| dir.starts_with(t)))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use std::fs;
+
+	use super::*;
+	use crate::testing::scratch;
+
+	#[test]
+	fn protects_roots_and_trees() {
+		assert_eq!(protected("/", &[]), Some(PathBuf::from("/")));
+		assert!(protected("/usr", &[]).is_some());
+		assert!(protected("/usr/bin", &[]).is_some());
+		assert!(protected("/tmp", &[]).is_some());
+	}
+
+	#[test]
+	fn protects_only_run_itself() {
+		let run = PathBuf::from("/run");
+
+		if run.is_dir() {
+			assert_eq!(protected(&run, &[]), fs::canonicalize(&run).ok());
+		}
+
+		// Those beneath it (such as removable media mounted beneath /run/media) are not, unless they are protected themselves.
+
+		let roots: Vec<PathBuf> = PROTECTED_ROOTS.iter().map(PathBuf::from).chain([env::temp_dir()]).collect();
+		let beneath = |dir: &Path| fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect::<Vec<_>>();
+
+		for dir in beneath(&run).into_iter().chain(beneath(Path::new("/run/media"))) {
+			if fs::canonicalize(&dir).is_ok_and(|c| c.starts_with(&run) && !roots.contains(&c)) {
+				assert_eq!(protected(&dir, &[]), None, "{} should not be protected", dir.display());
+			}
+		}
+	}
+
+	#[test]
+	fn protects_specified_beneath_temporary() {
+		let dir = scratch("protected-specified");
+		let inner = dir.join("inner");
+
+		fs::create_dir(&inner).unwrap();
+
+		assert_eq!(protected(&inner, &[]), None);
+		assert_eq!(protected(&inner, std::slice::from_ref(&dir)), fs::canonicalize(&dir).ok());
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
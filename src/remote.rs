@@ -0,0 +1,338 @@
+use std::{collections::HashMap, io, path::Path, time::Instant};
+
+use crate::{
+	changes::Change,
+	duplicates::Duplicates,
+	filter,
+	hashing::Hash,
+	history,
+	journal::{self, Operation},
+	output::{self, say},
+	plan,
+	policy::{EmptyFiles, Keep},
+	Args, Mode, OrganiseError, OrganiseResult,
+};
+
+/// Represents a particular file within a remote directory.
+#[derive(Debug, Clone)]
+pub struct Entry {
+	/// The name of the file within the directory.
+	pub name: String,
+
+	/// The checksum of the contents of the file, should it already be known from listing the directory (such as the ETag of an object).
+	pub checksum: Option<String>,
+}
+
+/// Represents a remote directory, through which files are discovered and organised without mounting it.
+pub trait Backend {
+	/// Returns the location of the directory, for display.
+	fn location(&self) -> &str;
+
+	/// Lists the regular files within the directory.
+	fn list(&self) -> io::Result<Vec<Entry>>;
+
+	/// Computes the (hexadecimal MD5) checksum of the contents of the specified file.
+	fn checksum(&self, entry: &Entry) -> io::Result<String>;
+
+	/// Returns whether or not a file with the specified name exists within the directory.
+	fn exists(&self, name: &str) -> io::Result<bool>;
+
+	/// Renames the specified file within the directory.
+	fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+
+	/// Removes the specified file from the directory.
+	fn remove(&self, name: &str) -> io::Result<()>;
+}
+
+/// Returns whether or not the specified location is a remote directory (such as `sftp://host/path`), rather than a local path.
+pub fn is_remote<T>(location: T) -> bool
+where
+	T: AsRef<Path>,
+{
+	location.as_ref().to_str().is_some_and(|l| l.contains("://"))
+}
+
+/// Connects to the remote directory at the specified location to organise it with the specified arguments, or nothing if the location is a local path.
+pub fn open<T>(location: T, args: &Args) -> Result<Option<Box<dyn Backend>>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	if !is_remote(&location) {
+		return Ok(None);
+	}
+
+	refuse(args)?;
+
+	#[cfg(feature = "sftp")]
+	let remote_hash = args.remote_hash;
+
+	#[cfg(not(feature = "sftp"))]
+	let remote_hash = false;
+
+	connect(location, remote_hash).map_err(OrganiseError::FailedToAccessRemote)
+}
+
+/// Connects to the remote directory at the specified location, computing checksums upon the server where possible should it be specified to, or nothing if the location is a local path.
+#[cfg_attr(not(feature = "sftp"), allow(unused_variables))]
+fn connect<T>(location: T, remote_hash: bool) -> io::Result<Option<Box<dyn Backend>>>
+where
+	T: AsRef<Path>,
+{
+	let Some((scheme, _)) = location.as_ref().to_str().and_then(|l| l.split_once("://")) else {
+		return Ok(None);
+	};
+
+	match scheme {
+		#[cfg(feature = "sftp")]
+		"sftp" => crate::sftp::Sftp::connect(location.as_ref().to_str().unwrap_or_default(), remote_hash).map(|b| Some(Box::new(b) as Box<dyn Backend>)),
+
+		#[cfg(feature = "s3")]
+		"s3" => crate::s3::S3::open(location.as_ref().to_str().unwrap_or_default()).map(|b| Some(Box::new(b) as Box<dyn Backend>)),
+
+		#[cfg(feature = "webdav")]
+		"dav" | "davs" => crate::webdav::WebDav::open(location.as_ref().to_str().unwrap_or_default()).map(|b| Some(Box::new(b) as Box<dyn Backend>)),
+
+		_ => Err(io::Error::new(io::ErrorKind::Unsupported, format!("{}:// directories are not supported by this build", scheme))),
+	}
+}
+
+/// Refuses to organise a remote directory with the specified arguments should they ask for something which cannot be done remotely, before anything is connected to.
+fn refuse(args: &Args) -> Result<(), OrganiseError> {
+	let unsupported = |message: &str| Err(OrganiseError::FailedToAccessRemote(io::Error::new(io::ErrorKind::Unsupported, message.to_owned())));
+
+	// Checksums of remote files are only ever listed (or computed upon the server) as MD5.
+
+	if args.algorithm != Hash::Md5 {
+		return unsupported("remote directories can only be organised by MD5 checksums");
+	}
+
+	// Remote directories have no Recycle Bin (or Trash), nor a quarantine folder of their own to move duplicates into, so deleting them is all that can be done.
+
+	if args.duplicates != Duplicates::Delete {
+		return unsupported("duplicates within remote directories can only be deleted");
+	}
+
+	// Sizes are not listed alongside remote files, whereas patterns only need their names.
+
+	if args.min_size.is_some() || args.max_size.is_some() {
+		return unsupported("remote files cannot be filtered by size");
+	}
+
+	// Content is only ever moved into a store from the same file system, which a remote directory never shares.
+
+	if args.store.is_some() {
+		return unsupported("remote directories cannot be organised into a store");
+	}
+
+	// Everything else is only ever done to local directories, which would otherwise be silently ignored.
+
+	let local = [
+		(args.recursive, "--recursive"),
+		(args.watch, "--watch"),
+		(args.change_list.is_some(), "--change-list"),
+		(args.group_by.is_some(), "--group-by"),
+		(args.annex, "--annex"),
+		(!args.normalise.is_empty(), "--normalise"),
+		(args.keep != Keep::Existing, "--keep"),
+		(!args.prefer.is_empty(), "--prefer"),
+		(args.write_checksums.is_some(), "--write-checksums"),
+		(args.sidecars, "--sidecars"),
+		(!args.similar.is_empty(), "--similar"),
+		(args.report_archived, "--report-archived"),
+		(args.verify_checksums, "--verify-checksums"),
+		(args.protect_torrents, "--protect-torrents"),
+		(args.verify_sample.is_some(), "--verify-sample"),
+		(args.empty_files != EmptyFiles::Organise, "--empty-files"),
+		(args.remove_empty_older_than.is_some(), "--remove-empty-older-than"),
+	];
+
+	match local.iter().find(|(given, _)| *given) {
+		Some((_, flag)) => unsupported(&format!("{} is not supported for remote directories", flag)),
+		None => Ok(()),
+	}
+}
+
+/// Organises the specified remote directory, renaming each file to its checksum and removing duplicates.
+pub fn organise(backend: &dyn Backend, args: &Args) -> OrganiseResult {
+	// Remote directories have nowhere local to journal into, so their journal is written within the current directory instead, where duplicates deleted remotely are only recorded, as there is nothing to restore them from without downloading the files they duplicated.
+
+	if !args.dry_run {
+		journal::open(".");
+
+		say!("Duplicates deleted from <{}> cannot be restored by undoing, which only renames files back.", backend.location());
+	}
+
+	say!("Discovering files in <{}>...", backend.location());
+
+	let start = Instant::now();
+
+	let mut files = backend.list().map_err(OrganiseError::FailedToListDirectory)?;
+
+	files.retain(|e| filter::admits(&e.name, None, args));
+
+	// Check either every file or only the files where the name does not appear to be a hash.
+
+	let files: Vec<Entry> = match args.mode {
+		Mode::Full => files,
+		Mode::Fast => files.into_iter().filter(|e| Path::new(&e.name).file_stem().and_then(|n| n.to_str()).map(|n| !Hash::Md5.is_checksum(n)).unwrap_or(true)).collect(),
+	};
+
+	say!("Discovered {} files in {:#?}.", files.len(), start.elapsed());
+	say!("Organising {} files...", files.len());
+
+	// Remote connections are not shared between threads, so files are organised one at a time.
+
+	let mut failures = 0;
+
+	for entry in &files {
+		if let Err(e) = process(backend, entry, args.dry_run) {
+			crate::fail(Path::new(backend.location()).join(&entry.name), &e);
+
+			failures += 1;
+		}
+	}
+
+	match failures {
+		0 => Ok(()),
+		n => Err(OrganiseError::FailedToOrganiseFiles(n)),
+	}
+}
+
+/// Organises the specified remote file, or only reports what would be done to it during a dry run.
+fn process(backend: &dyn Backend, entry: &Entry, dry_run: bool) -> OrganiseResult {
+	let checksum = match &entry.checksum {
+		Some(checksum) => checksum.to_owned(),
+		None => backend.checksum(entry).map_err(OrganiseError::FailedToReadFile)?,
+	};
+
+	let checksum_name = match Path::new(&entry.name).extension().and_then(|e| e.to_str()) {
+		Some(extension) => format!("{}.{}", checksum, extension),
+		None => checksum.clone(),
+	};
+
+	if checksum_name == entry.name {
+		return Ok(());
+	}
+
+	let path = Path::new(backend.location()).join(&entry.name);
+	let exists = backend.exists(&checksum_name).map_err(OrganiseError::FailedToReadFile)? || (dry_run && !plan::claim(Path::new(backend.location()).join(&checksum_name)));
+
+	if exists && dry_run {
+		say!("Would delete duplicate file <{}/{}>.", backend.location(), entry.name);
+		output::change(&Change::Remove(path.clone()));
+		history::disposed(&path, 0);
+	} else if !exists && dry_run {
+		say!("Would rename file <{}/{}> to <{}/{}>.", backend.location(), entry.name, backend.location(), checksum_name);
+		output::change(&Change::Rename(path.clone(), path.with_file_name(&checksum_name)));
+		history::organised(&path);
+	} else if exists {
+		output::duplicate_group(Some(&checksum), &[(format!("{}/{}", backend.location(), checksum_name), None), (format!("{}/{}", backend.location(), entry.name), None)]);
+
+		say!("Deleting duplicate file <{}/{}>...", backend.location(), entry.name);
+
+		backend.remove(&entry.name).map_err(OrganiseError::FailedToRemoveDuplicateFile)?;
+
+		journal::removed(&path, Some(&path.with_file_name(&checksum_name)), &checksum);
+		output::change(&Change::Remove(path.clone()));
+
+		// The sizes of remote files are not listed, so nothing is known to be reclaimed.
+
+		history::disposed(&path, 0);
+	} else {
+		say!("Organising new file <{}/{}>...", backend.location(), entry.name);
+
+		backend.rename(&entry.name, &checksum_name).map_err(OrganiseError::FailedToRenameNewFile)?;
+
+		journal::renamed(&path, path.with_file_name(&checksum_name), &checksum);
+		output::change(&Change::Rename(path.clone(), path.with_file_name(&checksum_name)));
+		history::organised(&path);
+	}
+
+	Ok(())
+}
+
+/// Undoes the specified journalled operation upon a remote file, connecting to the directory holding it unless already connected, returning whether or not it was undone rather than skipped (as the file already exists).
+pub fn undo(entry: &journal::Entry, connections: &mut HashMap<String, Box<dyn Backend>>) -> io::Result<bool> {
+	let (Some(location), Some(name)) = (entry.file.parent().and_then(|p| p.to_str()), entry.file.file_name().and_then(|n| n.to_str())) else {
+		return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid remote location"));
+	};
+
+	if !connections.contains_key(location) {
+		let backend = connect(location, false)?.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid remote location"))?;
+
+		connections.insert(location.to_owned(), backend);
+	}
+
+	let backend = connections[location].as_ref();
+
+	if backend.exists(name)? {
+		say!("Skipping file <{}>, which already exists.", entry.file.display());
+
+		return Ok(false);
+	}
+
+	match &entry.operation {
+		Operation::Rename(to) => {
+			say!("Renaming file <{}> back to <{}>...", to.display(), entry.file.display());
+
+			let to = to.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+			backend.rename(to, name).map(|_| true)
+		}
+
+		// Remote directories cannot copy files within themselves, so there is nothing to restore a duplicate from without downloading it.
+
+		Operation::Remove(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "duplicates removed from remote directories cannot be restored")),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use clap::Parser;
+
+	use super::*;
+
+	/// Parses the specified arguments for organising a remote directory, alongside the defaults of the command line.
+	fn args(arguments: &[&str]) -> Args {
+		Args::parse_from([env!("CARGO_PKG_NAME"), "--dir", "sftp://host/path"].iter().chain(arguments))
+	}
+
+	#[test]
+	fn is_remote_only_with_scheme() {
+		assert!(is_remote("sftp://host/path"));
+		assert!(is_remote("s3://bucket/prefix"));
+		assert!(!is_remote("/srv/photos"));
+		assert!(!is_remote("photos"));
+	}
+
+	#[test]
+	fn refuses_nothing_by_default() {
+		assert!(refuse(&args(&[])).is_ok());
+		assert!(refuse(&args(&["--mode", "full", "--include", "*.jpg", "--dry-run"])).is_ok());
+	}
+
+	#[test]
+	fn refuses_what_cannot_be_done_remotely() {
+		let refused: &[&[&str]] = &[
+			&["--algorithm", "sha256"],
+			&["--duplicates", "trash"],
+			&["--min-size", "1K"],
+			&["--store", "store"],
+			&["--recursive"],
+			&["--watch"],
+			&["--change-list", "changes.sh"],
+			&["--group-by", "tags"],
+			&["--annex"],
+			&["--normalise", "pixels"],
+			&["--keep", "shortest"],
+			&["--prefer", "originals/"],
+			&["--write-checksums"],
+			&["--sidecars"],
+			&["--remove-empty-older-than", "30"],
+		];
+
+		for arguments in refused {
+			assert!(refuse(&args(arguments)).is_err(), "{:?} should be refused", arguments);
+		}
+	}
+}
@@ -0,0 +1,213 @@
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{attributes, changes::Change, history, journal, named_checksum, output::{self, say, warning}, process, sidecar::Sidecars, storage, store::Store, Args, Mode, OrganiseError, OrganiseResult};
+
+/// Determines the duplicate finder which produced a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Finder {
+	/// Indicates fdupes (or jdupes), which lists each group of paths separated by blank lines.
+	Fdupes,
+
+	/// Indicates rdfind, which lists every file alongside the identifier of the first occurrence of its content.
+	Rdfind,
+
+	/// Indicates fclones, which lists each group of indented paths beneath its checksum and size.
+	Fclones,
+}
+
+impl Finder {
+	/// Determines the duplicate finder which produced the specified report from its contents.
+	fn of(report: &str) -> Self {
+		let mut lines = report.lines().map(str::trim_end).filter(|l| !l.is_empty());
+
+		match lines.next() {
+			Some(l) if l.starts_with("# Report by fclones") => Self::Fclones,
+			Some(l) if l.starts_with("# Automatically generated") || l.starts_with("# duptype") || l.starts_with("DUPTYPE_") => Self::Rdfind,
+			_ => Self::Fdupes,
+		}
+	}
+
+	/// Parses the groups of duplicate files from the specified report.
+	fn parse(&self, report: &str) -> Vec<Vec<PathBuf>> {
+		match self {
+			Self::Fdupes => {
+				// The sizes of groups are given on a line of their own when requested (with `--size`).
+
+				report
+					.split("\n\n")
+					.map(|g| g.lines().filter(|l| !l.is_empty() && !l.ends_with(" bytes each:") && !l.ends_with(" byte each:")).map(PathBuf::from).collect::<Vec<_>>())
+					.filter(|g| g.len() > 1)
+					.collect()
+			}
+			Self::Rdfind => {
+				let mut groups: Vec<(i64, Vec<PathBuf>)> = Vec::new();
+
+				// Each line holds the type, identifier, depth, size, device, inode and priority of a file before its path, which may contain spaces.
+
+				for line in report.lines().filter(|l| l.starts_with("DUPTYPE_")) {
+					let mut fields = line.splitn(8, ' ');
+					let (Some(id), Some(path)) = (fields.nth(1).and_then(|i| i.parse::<i64>().ok()), fields.nth(5)) else {
+						continue;
+					};
+
+					match groups.iter_mut().find(|(i, _)| *i == id.abs()) {
+						Some((_, group)) => group.push(PathBuf::from(path)),
+						None => groups.push((id.abs(), vec![PathBuf::from(path)])),
+					}
+				}
+
+				groups.into_iter().map(|(_, g)| g).filter(|g| g.len() > 1).collect()
+			}
+			Self::Fclones => {
+				let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+
+				for line in report.lines().filter(|l| !l.starts_with('#') && !l.is_empty()) {
+					match line.strip_prefix("    ") {
+						Some(path) => {
+							if let Some(group) = groups.last_mut() {
+								group.push(PathBuf::from(path));
+							}
+						}
+						None => groups.push(Vec::new()),
+					}
+				}
+
+				groups.into_iter().filter(|g| g.len() > 1).collect()
+			}
+		}
+	}
+}
+
+/// Organises the groups of duplicate files within the specified report (from fdupes, rdfind or fclones), keeping one file of each according to the keep policy and organising it.
+pub fn organise<T>(report: T, args: &Args) -> OrganiseResult
+where
+	T: AsRef<Path>,
+{
+	say!("Reading duplicates from <{}>...", report.as_ref().display());
+
+	// The files listed may lie anywhere, so their removals are journalled alongside the report instead.
+
+	if !args.dry_run {
+		let dir = report.as_ref().parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+		journal::open(dir);
+	}
+
+	let report = fs::read_to_string(report).map_err(OrganiseError::FailedToReadFile)?.replace("\r\n", "\n");
+	let groups = Finder::of(&report).parse(&report);
+
+	say!("Found {} groups of {} duplicate files.", groups.len(), groups.iter().map(Vec::len).sum::<usize>());
+
+	if let Some(limit) = args.io_concurrency {
+		storage::limit(limit);
+	}
+
+	let store = args.store.as_ref().filter(|_| !args.dry_run).map(Store::open).transpose()?;
+
+	#[cfg(all(target_os = "linux", feature = "selinux"))]
+	let store = store.map(|s| s.labelled(args.selinux));
+
+	let failures = groups
+		.par_iter()
+		.filter(|group| match organise_group(group, store.as_ref(), args) {
+			Ok(()) => false,
+			Err(e) => {
+				warning!("Failed to organise duplicates of <{}>: {}.", group[0].display(), e);
+
+				output::failure(&group[0].display().to_string(), &e.to_string());
+				history::failed(&group[0]);
+
+				true
+			}
+		})
+		.count();
+
+	match failures {
+		0 => Ok(()),
+		n => Err(OrganiseError::FailedToOrganiseFiles(n)),
+	}
+}
+
+/// Organises the specified group of duplicate files.
+fn organise_group(group: &[PathBuf], store: Option<&Store>, args: &Args) -> OrganiseResult {
+	let group: Vec<&PathBuf> = group.iter().filter(|f| f.is_file()).collect();
+
+	let Some(first) = group.first() else {
+		return Ok(());
+	};
+
+	// The report is trusted in fast mode so long as sizes still agree, whereas full mode compares the entire contents once more, as files may have changed since it was made. Contents are only compared once sizes agree, as files of differing sizes can never match.
+
+	let size = |file: &Path| file.metadata().map(|m| m.len());
+
+	let expected_size = size(first).map_err(OrganiseError::FailedToReadFile)?;
+	let expected = match args.mode {
+		Mode::Fast => None,
+		Mode::Full => Some(args.algorithm.compute(first).map_err(OrganiseError::FailedToReadFile)?),
+	};
+
+	let matches = |file: &Path| -> io::Result<bool> {
+		if size(file)? != expected_size {
+			return Ok(false);
+		}
+
+		match &expected {
+			Some(expected) => args.algorithm.compute(file).map(|c| c == *expected),
+			None => Ok(true),
+		}
+	};
+
+	let mut duplicates: Vec<&PathBuf> = vec![*first];
+
+	for file in &group[1..] {
+		match matches(file) {
+			Ok(true) => duplicates.push(*file),
+			Ok(false) => say!("Skipping file <{}>, which no longer matches its group.", file.display()),
+			Err(e) => warning!("Failed to read file <{}>: {}.", file.display(), e),
+		}
+	}
+
+	// The file which has already been organised is the existing one, as though the others had been found alongside it.
+
+	let sizes: Vec<(String, Option<u64>)> = duplicates.iter().map(|d| (d.display().to_string(), d.metadata().ok().map(|m| m.len()))).collect();
+
+	output::duplicate_group(expected.as_deref(), &sizes);
+
+	let existing = duplicates.iter().copied().find(|f| named_checksum(f, args.algorithm).is_some()).unwrap_or(duplicates[0]);
+	let kept = duplicates.iter().copied().fold(existing, |kept, candidate| if args.keep.prefers(kept, candidate, &args.prefer) { candidate } else { kept });
+
+	for duplicate in duplicates.iter().filter(|d| **d != kept) {
+		let size = duplicate.metadata().map(|m| m.len()).unwrap_or_default();
+
+		if args.dry_run {
+			say!("Would {} duplicate file <{}>.", args.duplicates.action(), duplicate.display());
+			output::change(&Change::Remove(duplicate.to_path_buf()));
+			history::disposed(duplicate, size);
+
+			continue;
+		}
+
+		say!("{} duplicate file <{}>...", args.duplicates.verb(), duplicate.display());
+
+		#[cfg(target_os = "linux")]
+		if args.duplicates == crate::duplicates::Duplicates::Dedupe {
+			args.duplicates.dispose(duplicate, kept, None).map_err(OrganiseError::FailedToDeduplicateFile)?;
+			history::disposed(duplicate, size);
+			continue;
+		}
+
+		attributes::preserve(duplicate, kept);
+		args.duplicates.dispose(duplicate, kept, args.quarantine.as_deref()).map_err(OrganiseError::FailedToRemoveDuplicateFile)?;
+		journal::removed(duplicate, Some(kept), expected.as_deref().unwrap_or_default());
+		output::change(&Change::Remove(duplicate.to_path_buf()));
+		history::disposed(duplicate, size);
+	}
+
+	process(kept, &Sidecars::default(), &HashMap::new(), store, None, None, None, args)
+}
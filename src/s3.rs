@@ -0,0 +1,294 @@
+use std::{
+	env,
+	fmt::Write,
+	io::{self, Read},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use ureq::http::Response;
+
+use crate::{
+	dates,
+	remote::{Backend, Entry},
+};
+
+/// The region assumed should none be configured.
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// The size of the blocks of SHA-256, over which HMAC is defined.
+const BLOCK_SIZE: usize = 64;
+
+/// Represents a bucket (and prefix within it) of S3-compatible object storage, treated as a directory of files.
+pub struct S3 {
+	/// The location of the directory, as specified.
+	location: String,
+
+	/// The name of the bucket.
+	bucket: String,
+
+	/// The prefix of the objects within the bucket which form the directory, ending with a separator unless empty.
+	prefix: String,
+
+	/// The base URL which object keys are appended to.
+	base: String,
+
+	/// The region of the bucket.
+	region: String,
+
+	/// The access key identifier to sign requests with.
+	access_key: String,
+
+	/// The secret access key to sign requests with.
+	secret_key: String,
+
+	/// The session token of temporary credentials, if any.
+	session_token: Option<String>,
+}
+
+impl S3 {
+	/// Opens the bucket (and prefix) at the specified location (such as `s3://bucket/prefix`), configured from the standard AWS environment variables.
+	///
+	/// Buckets are addressed by virtual host on AWS, and by path on other services (specified by `AWS_ENDPOINT_URL`).
+	pub fn open(location: &str) -> io::Result<Self> {
+		let missing = |what: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("missing {}", what));
+
+		let rest = location.strip_prefix("s3://").ok_or_else(|| missing("s3:// scheme"))?;
+		let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+
+		if bucket.is_empty() {
+			return Err(missing("bucket"));
+		}
+
+		let prefix = match prefix.trim_matches('/') {
+			"" => String::new(),
+			prefix => format!("{}/", prefix),
+		};
+
+		let region = env::var("AWS_REGION").or_else(|_| env::var("AWS_DEFAULT_REGION")).unwrap_or_else(|_| DEFAULT_REGION.to_owned());
+
+		let base = match env::var("AWS_ENDPOINT_URL") {
+			Ok(endpoint) => format!("{}/{}", endpoint.trim_end_matches('/'), bucket),
+			Err(_) => format!("https://{}.s3.{}.amazonaws.com", bucket, region),
+		};
+
+		Ok(Self {
+			location: location.trim_end_matches('/').to_owned(),
+			bucket: bucket.to_owned(),
+			prefix,
+			base,
+			region,
+			access_key: env::var("AWS_ACCESS_KEY_ID").map_err(|_| missing("AWS_ACCESS_KEY_ID"))?,
+			secret_key: env::var("AWS_SECRET_ACCESS_KEY").map_err(|_| missing("AWS_SECRET_ACCESS_KEY"))?,
+			session_token: env::var("AWS_SESSION_TOKEN").ok(),
+		})
+	}
+
+	/// Returns the URL of the specified object key, alongside its path (for signing).
+	fn url(&self, key: &str, query: &[(&str, &str)]) -> (String, String) {
+		let url = format!("{}/{}", self.base, encode(key, false));
+		let query = canonical_query(query);
+		let url = if query.is_empty() { url } else { format!("{}?{}", url, query) };
+
+		(url, query)
+	}
+
+	/// Performs a signed request with the specified method upon the specified object key.
+	fn request(&self, method: &str, key: &str, query: &[(&str, &str)], extra: &[(&str, String)]) -> io::Result<Response<ureq::Body>> {
+		let (url, canonical_query) = self.url(key, query);
+
+		let authority = url.split_once("://").map(|(_, r)| r).unwrap_or(&url);
+		let (host, path) = authority.split_at(authority.find('/').unwrap_or(authority.len()));
+		let path = path.split_once('?').map(|(p, _)| p).unwrap_or(path);
+
+		let (date, time) = timestamp();
+		let stamp = format!("{}T{}Z", date, time);
+
+		let mut headers: Vec<(String, String)> = vec![("host".to_owned(), host.to_owned()), ("x-amz-content-sha256".to_owned(), "UNSIGNED-PAYLOAD".to_owned()), ("x-amz-date".to_owned(), stamp.to_owned())];
+
+		if let Some(token) = &self.session_token {
+			headers.push(("x-amz-security-token".to_owned(), token.to_owned()));
+		}
+
+		headers.extend(extra.iter().map(|(k, v)| (k.to_string(), v.to_owned())));
+		headers.sort();
+
+		// Requests are signed with version 4 of the AWS signature scheme, leaving the (empty or streamed) payload unsigned.
+
+		let signed_headers = headers.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+		let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+		let canonical_request = format!("{}\n{}\n{}\n{}\n{}\nUNSIGNED-PAYLOAD", method, path, canonical_query, canonical_headers, signed_headers);
+
+		let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+		let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", stamp, scope, hex(&Sha256::digest(canonical_request.as_bytes())));
+
+		let key = [date.as_bytes(), self.region.as_bytes(), b"s3", b"aws4_request"].iter().fold(format!("AWS4{}", self.secret_key).into_bytes(), |key, part| hmac(&key, part));
+		let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+
+		let authorization = format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", self.access_key, scope, signed_headers, signature);
+
+		let mut request = ureq::http::Request::builder().method(method).uri(&url).header("authorization", authorization);
+
+		for (name, value) in headers.iter().filter(|(k, _)| k != "host") {
+			request = request.header(name, value);
+		}
+
+		let request = request.body(()).map_err(io::Error::other)?;
+
+		ureq::run(request).map_err(|e| match e {
+			ureq::Error::StatusCode(404) => io::Error::new(io::ErrorKind::NotFound, "no such object"),
+			e => e.into_io(),
+		})
+	}
+}
+
+impl Backend for S3 {
+	fn location(&self) -> &str {
+		&self.location
+	}
+
+	fn list(&self) -> io::Result<Vec<Entry>> {
+		let contents = Regex::new(r"(?s)<Contents>(.*?)</Contents>").unwrap();
+		let key = Regex::new(r"<Key>(.*?)</Key>").unwrap();
+		let etag = Regex::new(r"<ETag>(.*?)</ETag>").unwrap();
+		let token = Regex::new(r"<NextContinuationToken>(.*?)</NextContinuationToken>").unwrap();
+
+		let mut entries = Vec::new();
+		let mut continuation: Option<String> = None;
+
+		// Objects are listed a page at a time, with only those directly beneath the prefix (as if it were a directory) included.
+
+		loop {
+			let mut query = vec![("delimiter", "/"), ("list-type", "2"), ("prefix", self.prefix.as_str())];
+
+			if let Some(token) = &continuation {
+				query.push(("continuation-token", token));
+			}
+
+			let body = self.request("GET", "", &query, &[])?.into_body().read_to_string().map_err(|e| e.into_io())?;
+
+			for object in contents.captures_iter(&body) {
+				let Some(name) = key.captures(&object[1]).map(|k| unescape(&k[1])) else {
+					continue;
+				};
+
+				let Some(name) = name.strip_prefix(&self.prefix).filter(|n| !n.is_empty() && !n.ends_with('/')) else {
+					continue;
+				};
+
+				// The ETag of an object is the MD5 checksum of its contents, unless it was uploaded in multiple parts.
+
+				let checksum = etag.captures(&object[1]).map(|e| unescape(&e[1]).trim_matches('"').to_ascii_lowercase()).filter(|e| e.len() == 32 && e.chars().all(|c| c.is_ascii_hexdigit()));
+
+				entries.push(Entry { name: name.to_owned(), checksum });
+			}
+
+			continuation = token.captures(&body).map(|t| unescape(&t[1]));
+
+			if continuation.is_none() {
+				return Ok(entries);
+			}
+		}
+	}
+
+	fn checksum(&self, entry: &Entry) -> io::Result<String> {
+		let mut response = self.request("GET", &format!("{}{}", self.prefix, entry.name), &[], &[])?;
+		let mut reader = response.body_mut().as_reader();
+		let mut context = md5::Context::new();
+		let mut buffer = [0u8; 64 * 1024];
+
+		loop {
+			let read = reader.read(&mut buffer)?;
+
+			if read == 0 {
+				break;
+			}
+
+			context.consume(&buffer[..read]);
+		}
+
+		Ok(format!("{:x}", context.compute()))
+	}
+
+	fn exists(&self, name: &str) -> io::Result<bool> {
+		match self.request("HEAD", &format!("{}{}", self.prefix, name), &[], &[]) {
+			Ok(_) => Ok(true),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+			Err(e) => Err(e),
+		}
+	}
+
+	fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+		// Objects cannot be renamed, so are instead copied (which is limited to objects of 5 GiB) before the original is deleted.
+
+		let source = format!("/{}/{}", self.bucket, encode(&format!("{}{}", self.prefix, from), false));
+
+		self.request("PUT", &format!("{}{}", self.prefix, to), &[], &[("x-amz-copy-source", source)])?;
+		self.remove(from)
+	}
+
+	fn remove(&self, name: &str) -> io::Result<()> {
+		self.request("DELETE", &format!("{}{}", self.prefix, name), &[], &[]).map(|_| ())
+	}
+}
+
+/// Percent-encodes the specified text as required by AWS signatures, optionally encoding separators too.
+fn encode(text: &str, separators: bool) -> String {
+	let mut encoded = String::with_capacity(text.len());
+
+	for byte in text.bytes() {
+		match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(char::from(byte)),
+			b'/' if !separators => encoded.push('/'),
+			_ => {
+				let _ = write!(encoded, "%{:02X}", byte);
+			}
+		}
+	}
+
+	encoded
+}
+
+/// Builds the canonical (sorted and encoded) form of the specified query parameters.
+fn canonical_query(query: &[(&str, &str)]) -> String {
+	let mut pairs: Vec<(String, String)> = query.iter().map(|(k, v)| (encode(k, true), encode(v, true))).collect();
+
+	pairs.sort();
+	pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&")
+}
+
+/// Reverses the escaping of the predefined entities within the specified XML text.
+fn unescape(text: &str) -> String {
+	text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&#34;", "\"").replace("&amp;", "&")
+}
+
+/// Computes the HMAC-SHA256 of the specified message with the specified key.
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+	let mut block = [0u8; BLOCK_SIZE];
+
+	if key.len() > BLOCK_SIZE {
+		block[..32].copy_from_slice(&Sha256::digest(key));
+	} else {
+		block[..key.len()].copy_from_slice(key);
+	}
+
+	let inner = Sha256::new().chain_update(block.map(|b| b ^ 0x36)).chain_update(message).finalize();
+
+	Sha256::new().chain_update(block.map(|b| b ^ 0x5c)).chain_update(inner).finalize().to_vec()
+}
+
+/// Formats the specified bytes as lowercase hexadecimal.
+fn hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Returns the current date (as `YYYYMMDD`) and time (as `HHMMSS`) in UTC.
+fn timestamp() -> (String, String) {
+	let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default() as i64;
+	let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+	let (year, month, day) = dates::civil(days);
+
+	(format!("{:04}{:02}{:02}", year, month, day), format!("{:02}{:02}{:02}", time / 3600, time % 3600 / 60, time % 60))
+}
@@ -0,0 +1,93 @@
+use std::{
+	collections::hash_map::RandomState,
+	hash::BuildHasher,
+	io,
+	path::PathBuf,
+	str::FromStr,
+	sync::Mutex,
+};
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+	checksums::Outcome,
+	hashing::Hash,
+	OrganiseError,
+};
+
+/// Represents a percentage of files, such as `5%` (where the sign may be omitted).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Percentage(f64);
+
+impl FromStr for Percentage {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let percentage: f64 = s.trim().trim_end_matches('%').parse().map_err(|_| format!("invalid percentage `{}`", s))?;
+
+		match percentage {
+			p if (0.0..=100.0).contains(&p) => Ok(Self(p)),
+			_ => Err(format!("percentage `{}` is not between 0% and 100%", s)),
+		}
+	}
+}
+
+/// Represents the organised files from which a sample is verified once organising has finished, along with the checksums they were named by.
+#[derive(Debug)]
+pub struct Sample {
+	/// The percentage of the organised files to verify.
+	percentage: Percentage,
+
+	/// The algorithm the organised files were named by.
+	algorithm: Hash,
+
+	/// The organised files and the checksums of their contents.
+	files: Mutex<Vec<(PathBuf, String)>>,
+}
+
+impl Sample {
+	/// Creates an empty sample of the specified percentage of organised files, which are named by the specified algorithm.
+	pub fn new(percentage: Percentage, algorithm: Hash) -> Self {
+		Self {
+			percentage,
+			algorithm,
+			files: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Records that the specified file was organised under the specified checksum of its contents.
+	pub fn record(&self, file: PathBuf, checksum: String) {
+		self.files.lock().unwrap().push((file, checksum));
+	}
+
+	/// Returns the number of organised files recorded.
+	pub fn len(&self) -> usize {
+		self.files.lock().unwrap().len()
+	}
+
+	/// Reads a random sample of the recorded files again, verifying that their contents still match the checksums they were named by.
+	pub fn verify(&self) -> Result<Vec<(PathBuf, Outcome)>, OrganiseError> {
+		let mut files = self.files.lock().unwrap().clone();
+
+		// Ordering by a randomly keyed hash of each path shuffles the files differently upon every run, so that every file is eventually verified.
+
+		let state = RandomState::new();
+
+		files.sort_by_cached_key(|(f, _)| state.hash_one(f));
+		files.truncate((files.len() as f64 * self.percentage.0 / 100.0).ceil() as usize);
+
+		files
+			.into_par_iter()
+			.map(|(file, checksum)| {
+				let outcome = match self.algorithm.compute(&file) {
+					Ok(actual) if actual.eq_ignore_ascii_case(&checksum) => Outcome::Matched,
+					Ok(_) => Outcome::Mismatched,
+					Err(e) if e.kind() == io::ErrorKind::NotFound => Outcome::Missing,
+					Err(e) => return Err(OrganiseError::FailedToReadFile(e)),
+				};
+
+				Ok((file, outcome))
+			})
+			.collect()
+	}
+}
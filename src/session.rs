@@ -0,0 +1,99 @@
+use std::{
+	cell::RefCell,
+	collections::{BTreeMap, HashMap, HashSet},
+	ffi::OsString,
+	fs::File,
+	path::PathBuf,
+	sync::{Arc, LazyLock, Mutex, OnceLock},
+};
+
+use rayon::{ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::{
+	history::Tally,
+	output::{Listener, Output, Verbosity},
+	storage::Gate,
+};
+
+/// The session of anything run outside of any other, such as messages written before a session is started.
+static DEFAULT: LazyLock<Arc<Session>> = LazyLock::new(|| Arc::new(Session::new(Output::Text, Verbosity::Normal)));
+
+thread_local! {
+	/// The session which the current thread is running within, should it be running within one.
+	static CURRENT: RefCell<Option<Arc<Session>>> = const { RefCell::new(None) };
+}
+
+/// Represents the state of a particular run (such as one of several jobs, or a directory organised by an embedding application), which is kept apart from that of every other run within the same process.
+pub struct Session {
+	/// The form of the output written during this run.
+	pub output: Output,
+
+	/// The verbosity of the messages written during this run.
+	pub verbosity: Verbosity,
+
+	/// The listener given every event during this run, should there be one.
+	pub listener: Option<Listener>,
+
+	/// The journal which every rename and removal during this run is recorded within, once opened, along with the file itself once the first entry is written.
+	pub journal: Mutex<Option<(PathBuf, Option<File>)>>,
+
+	/// The names which files would have been organised under during this (dry) run, as they are never actually taken.
+	pub claimed: Mutex<HashSet<PathBuf>>,
+
+	/// The names of the folders which files have been grouped into during this run, by their case-folded paths.
+	pub groups: Mutex<HashMap<PathBuf, OsString>>,
+
+	/// The limit upon the number of files read at once during this run, should there be one.
+	pub gate: OnceLock<Gate>,
+
+	/// The files organised and duplicates disposed of during this run, by the (lowercase) extension of each file.
+	pub extensions: Mutex<BTreeMap<String, Tally>>,
+
+	/// The files organised and duplicates disposed of during this run, by the folder holding each file.
+	pub folders: Mutex<BTreeMap<PathBuf, Tally>>,
+}
+
+impl Session {
+	/// Creates a new session writing output of the specified form and verbosity.
+	pub fn new(output: Output, verbosity: Verbosity) -> Self {
+		Self {
+			output,
+			verbosity,
+			listener: None,
+			journal: Mutex::new(None),
+			claimed: Mutex::new(HashSet::new()),
+			groups: Mutex::new(HashMap::new()),
+			gate: OnceLock::new(),
+			extensions: Mutex::new(BTreeMap::new()),
+			folders: Mutex::new(BTreeMap::new()),
+		}
+	}
+
+	/// Specifies the listener to give every event during this session.
+	pub fn listen(mut self, listener: Listener) -> Self {
+		self.listener = Some(listener);
+		self
+	}
+
+	/// Runs the specified operation within this session, upon a pool of the specified number of threads (or one for each processor, should it not be specified) of its own.
+	///
+	/// Every thread of the pool runs within this session, so that the operation (along with anything it runs in parallel) never shares the state of another session.
+	pub fn run<R, F>(self: &Arc<Self>, threads: Option<usize>, operation: F) -> Result<R, ThreadPoolBuildError>
+	where
+		F: FnOnce() -> R + Send,
+		R: Send,
+	{
+		let session = Arc::clone(self);
+		let pool = ThreadPoolBuilder::new()
+			.num_threads(threads.unwrap_or_default())
+			.start_handler(move |_| CURRENT.with(|c| *c.borrow_mut() = Some(Arc::clone(&session))))
+			.build()?;
+
+		Ok(pool.install(operation))
+	}
+}
+
+/// Returns the session which the current thread is running within.
+pub fn current() -> Arc<Session> {
+	CURRENT.with(|c| c.borrow().clone()).unwrap_or_else(|| Arc::clone(&DEFAULT))
+}
@@ -0,0 +1,206 @@
+use std::{
+	env,
+	io::{self, Read},
+	net::TcpStream,
+	path::{Path, PathBuf},
+};
+
+use ssh2::{CheckResult, ErrorCode, KnownHostFileKind, Session};
+
+use crate::remote::{Backend, Entry};
+
+/// The default port of SSH servers.
+const DEFAULT_PORT: u16 = 22;
+
+/// The status code of SFTP operations on files which do not exist.
+const NO_SUCH_FILE: i32 = 2;
+
+/// The private keys tried in turn (within the `.ssh` folder of the home directory) should no SSH agent be able to authenticate.
+const IDENTITIES: &[&str] = &["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// The environment variable holding the password to authenticate with, should no key be able to.
+const PASSWORD_VARIABLE: &str = "DIRECTORY_ORGANISER_SFTP_PASSWORD";
+
+/// Represents a remote directory accessed over SFTP.
+pub struct Sftp {
+	/// The location of the directory, as specified.
+	location: String,
+
+	/// The session with the server, through which remote commands are run.
+	session: Session,
+
+	/// The SFTP channel of the session.
+	sftp: ssh2::Sftp,
+
+	/// The path of the directory on the server.
+	dir: PathBuf,
+
+	/// Whether or not to compute checksums by running `md5sum` on the server, rather than streaming contents over the connection.
+	remote_hash: bool,
+}
+
+impl Sftp {
+	/// Connects to the directory at the specified location (such as `sftp://user@host:22/path`), verifying the server against the known hosts of the user.
+	pub fn connect(location: &str, remote_hash: bool) -> io::Result<Self> {
+		let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidInput, reason.to_owned());
+
+		let rest = location.strip_prefix("sftp://").ok_or_else(|| invalid("not an sftp:// location"))?;
+		let (authority, dir) = rest.split_once('/').ok_or_else(|| invalid("missing path"))?;
+		let (user, host) = match authority.rsplit_once('@') {
+			Some((user, host)) => (user.to_owned(), host),
+			None => (env::var("USER").or_else(|_| env::var("USERNAME")).map_err(|_| invalid("missing user"))?, authority),
+		};
+		let (host, port) = match host.rsplit_once(':') {
+			Some((host, port)) => (host, port.parse().map_err(|_| invalid("invalid port"))?),
+			None => (host, DEFAULT_PORT),
+		};
+
+		// Paths beginning with a tilde are relative to the home directory of the user, which is the initial directory of the session.
+
+		let dir = match dir.strip_prefix("~/") {
+			Some(relative) => PathBuf::from(relative),
+			None if dir == "~" => PathBuf::from("."),
+			None => Path::new("/").join(dir),
+		};
+
+		let mut session = Session::new()?;
+
+		session.set_tcp_stream(TcpStream::connect((host, port))?);
+		session.handshake()?;
+
+		verify_host(&session, host, port)?;
+		authenticate(&session, &user)?;
+
+		let sftp = session.sftp()?;
+
+		Ok(Self {
+			location: location.trim_end_matches('/').to_owned(),
+			session,
+			sftp,
+			dir,
+			remote_hash,
+		})
+	}
+
+	/// Computes the checksum of the specified file by running `md5sum` on the server, should it be available.
+	fn remote_checksum(&self, path: &Path) -> Option<String> {
+		let mut channel = self.session.channel_session().ok()?;
+		let mut output = String::new();
+
+		channel.exec(&format!("md5sum -- '{}'", path.to_str()?.replace('\'', r"'\''"))).ok()?;
+		channel.read_to_string(&mut output).ok()?;
+		channel.wait_close().ok()?;
+
+		let checksum = output.split_whitespace().next()?.to_ascii_lowercase();
+
+		match channel.exit_status() {
+			Ok(0) if checksum.len() == 32 && checksum.chars().all(|c| c.is_ascii_hexdigit()) => Some(checksum),
+			_ => None,
+		}
+	}
+}
+
+impl Backend for Sftp {
+	fn location(&self) -> &str {
+		&self.location
+	}
+
+	fn list(&self) -> io::Result<Vec<Entry>> {
+		let entries = self.sftp.readdir(&self.dir)?;
+
+		Ok(entries
+			.into_iter()
+			.filter(|(_, stat)| stat.is_file())
+			.filter_map(|(path, _)| {
+				Some(Entry {
+					name: path.file_name()?.to_str()?.to_owned(),
+					checksum: None,
+				})
+			})
+			.collect())
+	}
+
+	fn checksum(&self, entry: &Entry) -> io::Result<String> {
+		let path = self.dir.join(&entry.name);
+
+		// Hashing on the server avoids transferring the contents, falling back to streaming them should the command not be available.
+
+		if let Some(checksum) = self.remote_hash.then(|| self.remote_checksum(&path)).flatten() {
+			return Ok(checksum);
+		}
+
+		let mut file = self.sftp.open(&path)?;
+		let mut context = md5::Context::new();
+		let mut buffer = [0u8; 64 * 1024];
+
+		loop {
+			let read = file.read(&mut buffer)?;
+
+			if read == 0 {
+				break;
+			}
+
+			context.consume(&buffer[..read]);
+		}
+
+		Ok(format!("{:x}", context.compute()))
+	}
+
+	fn exists(&self, name: &str) -> io::Result<bool> {
+		match self.sftp.stat(&self.dir.join(name)) {
+			Ok(_) => Ok(true),
+			Err(e) if e.code() == ErrorCode::SFTP(NO_SUCH_FILE) => Ok(false),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+		Ok(self.sftp.rename(&self.dir.join(from), &self.dir.join(to), None)?)
+	}
+
+	fn remove(&self, name: &str) -> io::Result<()> {
+		Ok(self.sftp.unlink(&self.dir.join(name))?)
+	}
+}
+
+/// Verifies the host key of the server against the known hosts of the user, refusing to continue should it be unknown or have changed.
+fn verify_host(session: &Session, host: &str, port: u16) -> io::Result<()> {
+	let mut known_hosts = session.known_hosts()?;
+	let file = home()?.join(".ssh").join("known_hosts");
+
+	known_hosts.read_file(&file, KnownHostFileKind::OpenSSH)?;
+
+	let (key, _) = session.host_key().ok_or_else(|| io::Error::other("the server did not present a host key"))?;
+
+	match known_hosts.check_port(host, port, key) {
+		CheckResult::Match => Ok(()),
+		CheckResult::NotFound => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("the host key of {} is not known (connect with ssh first)", host))),
+		CheckResult::Mismatch => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("the host key of {} has changed", host))),
+		CheckResult::Failure => Err(io::Error::other("failed to check the host key")),
+	}
+}
+
+/// Authenticates the session as the specified user, trying the SSH agent, then the private keys of the user, then the password from the environment.
+fn authenticate(session: &Session, user: &str) -> io::Result<()> {
+	if session.userauth_agent(user).is_ok() {
+		return Ok(());
+	}
+
+	let keys = home()?.join(".ssh");
+
+	for identity in IDENTITIES.iter().map(|i| keys.join(i)).filter(|i| i.exists()) {
+		if session.userauth_pubkey_file(user, None, &identity, None).is_ok() {
+			return Ok(());
+		}
+	}
+
+	match env::var(PASSWORD_VARIABLE) {
+		Ok(password) => Ok(session.userauth_password(user, &password)?),
+		Err(_) => Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("failed to authenticate as {}", user))),
+	}
+}
+
+/// Returns the home directory of the user.
+fn home() -> io::Result<PathBuf> {
+	env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))
+}
@@ -0,0 +1,143 @@
+use std::{
+	collections::HashMap,
+	ffi::OsString,
+	io,
+	os::windows::ffi::OsStringExt,
+	path::{Path, PathBuf},
+	process::Command,
+	sync::Mutex,
+};
+
+use windows::{core::HSTRING, Win32::Storage::FileSystem::GetVolumePathNameW};
+
+use crate::output::{say, warning};
+
+/// The error given when opening a file which another application holds open without sharing it (`ERROR_SHARING_VIOLATION`).
+const ERROR_SHARING_VIOLATION: i32 = 32;
+
+/// The error given when reading a region of a file which another application has locked (`ERROR_LOCK_VIOLATION`).
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// Represents a shadow copy (snapshot) of a volume.
+#[derive(Debug)]
+struct ShadowCopy {
+	/// The identifier of the shadow copy, by which it is deleted.
+	id: String,
+
+	/// The device of the shadow copy (such as `\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy1`), beneath which the volume appears as it was when copied.
+	device: PathBuf,
+}
+
+/// Represents the shadow copies made of volumes whilst organising, each made once a locked file is first found upon it and deleted once no longer needed.
+#[derive(Debug, Default)]
+pub struct ShadowCopies {
+	/// The shadow copy made of each volume, or none should it have failed to be made.
+	copies: Mutex<HashMap<PathBuf, Option<ShadowCopy>>>,
+}
+
+impl ShadowCopies {
+	/// Returns the path of the specified file within a shadow copy of its volume, making one should none exist yet.
+	pub fn path<T>(&self, file: T) -> io::Result<PathBuf>
+	where
+		T: AsRef<Path>,
+	{
+		let file = std::path::absolute(crate::paths::simplify(file))?;
+		let volume = volume(&file)?;
+		let relative = file.strip_prefix(&volume).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the file is not upon its volume"))?.to_owned();
+
+		let device = {
+			let mut copies = self.copies.lock().unwrap();
+
+			match copies.get(&volume) {
+				Some(Some(copy)) => copy.device.clone(),
+				Some(None) => return Err(io::Error::other("no shadow copy could be made of its volume")),
+				None => {
+					say!("Creating shadow copy of volume <{}>...", volume.display());
+
+					match create(&volume) {
+						Ok(copy) => {
+							let device = copy.device.clone();
+
+							copies.insert(volume, Some(copy));
+							device
+						}
+						Err(e) => {
+							copies.insert(volume, None);
+							return Err(e);
+						}
+					}
+				}
+			}
+		};
+
+		Ok(device.join(relative))
+	}
+}
+
+impl Drop for ShadowCopies {
+	fn drop(&mut self) {
+		for copy in self.copies.get_mut().unwrap().values().flatten() {
+			say!("Deleting shadow copy <{}>...", copy.id);
+
+			if let Err(e) = powershell(&format!("Get-CimInstance -ClassName Win32_ShadowCopy | Where-Object ID -eq '{}' | Remove-CimInstance", copy.id)) {
+				warning!("Failed to delete shadow copy <{}>: {}.", copy.id, e);
+			}
+		}
+	}
+}
+
+/// Returns whether or not the specified error was given for a file which another application holds open (or has locked).
+pub fn locked(error: &io::Error) -> bool {
+	matches!(error.raw_os_error(), Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION))
+}
+
+/// Returns the root of the volume upon which the specified (absolute) file resides, such as `C:\`.
+fn volume(file: &Path) -> io::Result<PathBuf> {
+	let mut buffer = [0u16; 261];
+
+	unsafe { GetVolumePathNameW(&HSTRING::from(file.as_os_str()), &mut buffer) }.map_err(io::Error::other)?;
+
+	let length = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+
+	Ok(PathBuf::from(OsString::from_wide(&buffer[..length])))
+}
+
+/// Creates a shadow copy of the specified volume.
+fn create(volume: &Path) -> io::Result<ShadowCopy> {
+	// Shadow copies are made through WMI rather than through the backup components of VSS, which are only exposed to C++, and either way require the process to be elevated.
+
+	let output = powershell(&format!(
+		"$r = Invoke-CimMethod -ClassName Win32_ShadowCopy -MethodName Create -Arguments @{{ Volume = '{}'; Context = 'ClientAccessible' }}; \
+		if ($r.ReturnValue -ne 0) {{ [Console]::Error.Write($r.ReturnValue); exit 1 }}; \
+		$c = Get-CimInstance -ClassName Win32_ShadowCopy | Where-Object ID -eq $r.ShadowID; \
+		Write-Output $c.ID $c.DeviceObject",
+		volume.display().to_string().replace('\'', "''")
+	))
+	.map_err(|e| match e.to_string().as_str() {
+		"1" => io::Error::new(io::ErrorKind::PermissionDenied, "access is denied (the application must be run as an administrator)"),
+		"4" | "5" => io::Error::new(io::ErrorKind::Unsupported, "the volume does not support shadow copies"),
+		"6" => io::Error::new(io::ErrorKind::StorageFull, "there is insufficient storage for the shadow copy"),
+		_ => e,
+	})?;
+
+	let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+
+	match (lines.next(), lines.next()) {
+		(Some(id), Some(device)) => Ok(ShadowCopy {
+			id: id.to_owned(),
+			device: PathBuf::from(device),
+		}),
+		_ => Err(io::Error::new(io::ErrorKind::InvalidData, "the shadow copy was not described")),
+	}
+}
+
+/// Runs the specified PowerShell script, returning its output.
+fn powershell(script: &str) -> io::Result<String> {
+	let output = Command::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", script]).output()?;
+
+	if !output.status.success() {
+		return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+	}
+
+	Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
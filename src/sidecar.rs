@@ -0,0 +1,196 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{output::say, OrganiseError};
+
+/// The extensions of files which accompany a primary file of the same name.
+const SIDECAR_EXTENSIONS: &[&str] = &["aae", "idx", "nfo", "thm", "xmp"];
+
+/// The extensions of subtitles, which may additionally be qualified by language or disposition (such as `movie.en.forced.srt`).
+const SUBTITLE_EXTENSIONS: &[&str] = &["ass", "smi", "srt", "ssa", "sub", "sup", "vtt"];
+
+/// The extensions of camera raw images, which may be accompanied by a JPEG of the same name.
+const RAW_EXTENSIONS: &[&str] = &["arw", "cr2", "cr3", "dng", "nef", "orf", "pef", "raf", "rw2", "srw"];
+
+/// The extensions of the JPEG images which accompany camera raw images.
+const JPEG_EXTENSIONS: &[&str] = &["jpeg", "jpg"];
+
+/// The extensions of the still images of Live Photos, which are accompanied by a short video.
+///
+/// JPEG images are left out, as a video of the same name is far more likely to be a film with its poster than a Live Photo.
+const LIVE_PHOTO_EXTENSIONS: &[&str] = &["heic", "heif"];
+
+/// The extensions of the short videos which accompany the still images of Live Photos.
+const LIVE_VIDEO_EXTENSIONS: &[&str] = &["mov"];
+
+/// The extensions of videos, which may be accompanied by subtitles and artwork.
+const VIDEO_EXTENSIONS: &[&str] = &["avi", "m2ts", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg", "ts", "webm", "wmv"];
+
+/// The extensions of artwork images.
+const ARTWORK_EXTENSIONS: &[&str] = &["jpeg", "jpg", "png", "tbn", "webp"];
+
+/// The kinds of artwork which accompany a particular video (such as `movie-poster.jpg`) or an entire folder (such as `poster.jpg`).
+const ARTWORK_KINDS: &[&str] = &["backdrop", "banner", "clearart", "clearlogo", "cover", "disc", "fanart", "folder", "landscape", "logo", "poster", "thumb"];
+
+/// Represents the companion (sidecar) files within a directory, indexed by the primary file they accompany.
+#[derive(Debug, Default)]
+pub struct Sidecars {
+	/// The companion files of each primary file, alongside the suffix following the name of the primary file.
+	companions: HashMap<PathBuf, Vec<(PathBuf, String)>>,
+
+	/// The files which either accompany a primary file or the directory itself.
+	sidecars: HashSet<PathBuf>,
+}
+
+impl Sidecars {
+	/// Creates a new index of the companion files amongst the specified files.
+	pub fn new(files: &[PathBuf]) -> Self {
+		let mut stems: HashMap<PathBuf, Vec<&Path>> = HashMap::new();
+		let mut index = Self::default();
+
+		for file in files {
+			stems.entry(file.with_extension("")).or_default().push(file);
+		}
+
+		for file in files {
+			if is_folder_artwork(file) {
+				index.sidecars.insert(file.to_owned());
+
+				continue;
+			}
+
+			let primary = candidates(file).into_iter().find_map(|(stem, suffix, accepts)| {
+				stems.get(&stem)?.iter().find(|p| *p != file && !is(p, SIDECAR_EXTENSIONS) && !is(p, SUBTITLE_EXTENSIONS) && accepts(p)).map(|p| (p.to_path_buf(), suffix))
+			});
+
+			if let Some((primary, suffix)) = primary {
+				index.companions.entry(primary).or_default().push((file.to_owned(), suffix));
+				index.sidecars.insert(file.to_owned());
+			}
+		}
+
+		index
+	}
+
+	/// Returns whether or not the specified file accompanies another (primary) file or the directory itself.
+	pub fn is_sidecar<T>(&self, file: T) -> bool
+	where
+		T: AsRef<Path>,
+	{
+		self.sidecars.contains(file.as_ref())
+	}
+
+	/// Returns the files which accompany the specified primary file, alongside the suffix following its name.
+	pub fn of<T>(&self, primary: T) -> &[(PathBuf, String)]
+	where
+		T: AsRef<Path>,
+	{
+		self.companions.get(primary.as_ref()).map(|c| c.as_slice()).unwrap_or_default()
+	}
+}
+
+/// Represents a rule determining whether a particular file can be accompanied by a companion.
+type Accepts = fn(&Path) -> bool;
+
+/// Returns whether or not the specified file has any of the specified extensions.
+fn is(file: &Path, extensions: &[&str]) -> bool {
+	file.extension()
+		.and_then(|e| e.to_str())
+		.map(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+		.unwrap_or(false)
+}
+
+/// Returns whether or not the specified file is artwork for the directory as a whole, which media players expect to remain in place.
+fn is_folder_artwork(file: &Path) -> bool {
+	let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+
+	is(file, ARTWORK_EXTENSIONS) && ARTWORK_KINDS.iter().any(|k| k.eq_ignore_ascii_case(stem))
+}
+
+/// Returns the possible primary files (as paths without an extension) that the specified file could accompany, alongside the suffix following the name of the primary file and the rule the primary file must satisfy.
+fn candidates(file: &Path) -> Vec<(PathBuf, String, Accepts)> {
+	let Some(name) = file.file_name().and_then(|n| n.to_str()) else {
+		return Vec::new();
+	};
+
+	let mut candidates: Vec<(PathBuf, String, Accepts)> = Vec::new();
+	let mut candidate = |stem: &str, accepts: Accepts| candidates.push((file.with_file_name(stem), name[stem.len()..].to_owned(), accepts));
+
+	let stem = file.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+
+	if is(file, SIDECAR_EXTENSIONS) {
+		candidate(stem, |_| true);
+	}
+
+	if is(file, JPEG_EXTENSIONS) {
+		candidate(stem, |p| is(p, RAW_EXTENSIONS));
+	}
+
+	if is(file, LIVE_VIDEO_EXTENSIONS) {
+		candidate(stem, |p| is(p, LIVE_PHOTO_EXTENSIONS));
+	}
+
+	// Subtitles may be qualified by any number of tags, so try each shorter name in turn.
+
+	if is(file, SUBTITLE_EXTENSIONS) {
+		let mut stem = stem;
+
+		loop {
+			candidate(stem, |p| is(p, VIDEO_EXTENSIONS));
+
+			match stem.rsplit_once('.') {
+				Some((shorter, _)) if !shorter.is_empty() => stem = shorter,
+				_ => break,
+			}
+		}
+	}
+
+	if is(file, ARTWORK_EXTENSIONS) {
+		if let Some((stem, _)) = stem.rsplit_once('-').filter(|(s, k)| !s.is_empty() && ARTWORK_KINDS.iter().any(|x| x.eq_ignore_ascii_case(k))) {
+			candidate(stem, |p| is(p, VIDEO_EXTENSIONS));
+		}
+	}
+
+	candidates
+}
+
+/// Moves the specified companion files alongside the specified (renamed) primary file, sharing its name.
+///
+/// Should any companion fail to be moved, those already moved are restored to their original names. Otherwise, the companions which were moved are returned alongside where they were moved to.
+pub fn follow<T>(companions: &[(PathBuf, String)], primary: T) -> Result<Vec<(PathBuf, PathBuf)>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let stem = primary.as_ref().file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+	let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+	let result = companions.iter().try_for_each(|(sidecar, suffix)| {
+		let target = primary.as_ref().with_file_name(format!("{}{}", stem, suffix));
+
+		if target.try_exists().map_err(OrganiseError::FailedToReadFile)? {
+			say!("Keeping sidecar file <{}> as <{}> already exists.", sidecar.display(), target.display());
+
+			return Ok(());
+		}
+
+		say!("Moving sidecar file <{}> to <{}>...", sidecar.display(), target.display());
+
+		fs::rename(sidecar, &target).map_err(OrganiseError::FailedToRenameSidecarFile)?;
+		moved.push((sidecar.to_owned(), target));
+
+		Ok(())
+	});
+
+	if result.is_err() {
+		for (sidecar, target) in moved.into_iter().rev() {
+			let _ = fs::rename(target, sidecar);
+		}
+
+		return result.map(|_| Vec::new());
+	}
+
+	Ok(moved)
+}
@@ -0,0 +1,380 @@
+use std::{
+	fs,
+	hash::{DefaultHasher, Hash, Hasher},
+	io,
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+use clap::ValueEnum;
+use image::imageops::FilterType;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{output::warning, OrganiseError};
+
+/// Determines the kind of near-duplicate content to search for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Similarity {
+	/// Indicates that images should be compared by their perceptual (difference) hash.
+	Images,
+
+	/// Indicates that videos should be compared by the perceptual hashes of frames sampled throughout (requires `ffmpeg`).
+	Videos,
+
+	/// Indicates that audio should be compared by the acoustic fingerprint of the recording (requires `fpcalc`).
+	Audio,
+
+	/// Indicates that text documents should be compared by the similarity hash of their normalised words.
+	Documents,
+}
+
+/// Represents a file which appears to contain the same content as others.
+#[derive(Debug, Clone)]
+pub struct SimilarFile {
+	/// The path of the file.
+	pub path: PathBuf,
+
+	/// The details of the file relevant to choosing which copy to keep, if any.
+	pub details: Option<String>,
+}
+
+/// Represents a group of files which appear to contain the same content.
+pub type SimilarGroup = Vec<SimilarFile>;
+
+/// Represents the fingerprint of a particular file.
+enum Fingerprint {
+	/// Indicates the perceptual hash of an image.
+	Image(u64),
+
+	/// Indicates the perceptual hashes of frames sampled from a video of the specified duration (in seconds).
+	Video(Vec<u64>, f64),
+
+	/// Indicates the acoustic fingerprint of a recording of the specified duration (in seconds).
+	Audio(Vec<u32>, f64),
+
+	/// Indicates the similarity hash of a text document.
+	Document(u64),
+}
+
+/// The maximum number of differing bits for two image (or frame) hashes to be considered similar.
+const IMAGE_THRESHOLD: u32 = 8;
+
+/// The maximum difference in duration (in seconds) for two videos (or recordings) to be considered similar.
+const DURATION_THRESHOLD: f64 = 2.0;
+
+/// The minimum proportion of matching bits for two acoustic fingerprints to be considered similar.
+const ACOUSTIC_THRESHOLD: f64 = 0.85;
+
+/// The maximum number of items by which two acoustic fingerprints are shifted to find their best alignment.
+const ALIGNMENT_OFFSET: isize = 8;
+
+/// The maximum number of differing bits for two document hashes to be considered similar.
+const DOCUMENT_THRESHOLD: u32 = 3;
+
+/// The number of consecutive words which form each feature of a document.
+const SHINGLE_LENGTH: usize = 3;
+
+/// The positions (as fractions of the duration) at which frames are sampled from videos.
+const FRAME_POSITIONS: &[f64] = &[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+
+/// The extensions of files which are treated as images.
+const IMAGE_EXTENSIONS: &[&str] = &["bmp", "gif", "jpeg", "jpg", "png", "tif", "tiff", "webp"];
+
+/// The extensions of files which are treated as videos.
+const VIDEO_EXTENSIONS: &[&str] = &["avi", "flv", "m2ts", "m4v", "mkv", "mov", "mp4", "mpeg", "mpg", "ts", "webm", "wmv"];
+
+/// The extensions of files which are treated as audio.
+const AUDIO_EXTENSIONS: &[&str] = &["aac", "aiff", "alac", "ape", "flac", "m4a", "mp3", "ogg", "opus", "wav", "wma"];
+
+/// The extensions of files which are treated as text documents.
+const DOCUMENT_EXTENSIONS: &[&str] = &["csv", "htm", "html", "log", "md", "rst", "rtf", "tex", "txt", "xml"];
+
+impl Similarity {
+	/// Returns whether or not the specified file is applicable to this kind of comparison.
+	pub fn applies_to<T>(&self, file: T) -> bool
+	where
+		T: AsRef<Path>,
+	{
+		let extensions = match self {
+			Self::Images => IMAGE_EXTENSIONS,
+			Self::Videos => VIDEO_EXTENSIONS,
+			Self::Audio => AUDIO_EXTENSIONS,
+			Self::Documents => DOCUMENT_EXTENSIONS,
+		};
+
+		file.as_ref()
+			.extension()
+			.and_then(|e| e.to_str())
+			.map(|e| extensions.iter().any(|x| x.eq_ignore_ascii_case(e)))
+			.unwrap_or(false)
+	}
+
+	/// Returns the noun used to describe files of this kind.
+	pub fn noun(&self) -> &'static str {
+		match self {
+			Self::Images => "images",
+			Self::Videos => "videos",
+			Self::Audio => "recordings",
+			Self::Documents => "documents",
+		}
+	}
+}
+
+/// Finds groups of similar files of the specified kind amongst the specified files.
+pub fn find_similar(kind: Similarity, files: &[PathBuf]) -> Vec<SimilarGroup> {
+	let fingerprints: Vec<(SimilarFile, Fingerprint)> = files
+		.into_par_iter()
+		.filter(|f| kind.applies_to(f))
+		.filter_map(|f| match fingerprint(kind, f) {
+			Ok(h) => Some(h),
+			Err(e) => {
+				warning!("Failed to fingerprint file <{}>: {}.", f.display(), e);
+				None
+			}
+		})
+		.collect();
+
+	// Group fingerprints together where any two are within the threshold of each other.
+
+	let mut parents: Vec<usize> = (0..fingerprints.len()).collect();
+
+	for a in 0..fingerprints.len() {
+		for b in (a + 1)..fingerprints.len() {
+			if fingerprints[a].1.is_similar(&fingerprints[b].1) {
+				let (ra, rb) = (root(&mut parents, a), root(&mut parents, b));
+
+				parents[rb] = ra;
+			}
+		}
+	}
+
+	let mut groups: Vec<SimilarGroup> = vec![Vec::new(); fingerprints.len()];
+
+	for (i, (file, _)) in fingerprints.iter().enumerate() {
+		let r = root(&mut parents, i);
+
+		groups[r].push(file.to_owned());
+	}
+
+	groups.into_iter().filter(|g| g.len() > 1).collect()
+}
+
+impl Fingerprint {
+	/// Returns whether or not this fingerprint is similar to the other specified fingerprint.
+	fn is_similar(&self, other: &Fingerprint) -> bool {
+		match (self, other) {
+			(Self::Image(a), Self::Image(b)) => (a ^ b).count_ones() <= IMAGE_THRESHOLD,
+			(Self::Video(a, x), Self::Video(b, y)) => {
+				let distance: u32 = a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum();
+
+				(x - y).abs() <= DURATION_THRESHOLD && a.len() == b.len() && distance <= IMAGE_THRESHOLD * a.len() as u32
+			}
+			(Self::Audio(a, x), Self::Audio(b, y)) => (x - y).abs() <= DURATION_THRESHOLD && acoustic_similarity(a, b) >= ACOUSTIC_THRESHOLD,
+			(Self::Document(a), Self::Document(b)) => (a ^ b).count_ones() <= DOCUMENT_THRESHOLD,
+			_ => false,
+		}
+	}
+}
+
+/// Computes the fingerprint of the specified file.
+fn fingerprint<T>(kind: Similarity, file: T) -> Result<(SimilarFile, Fingerprint), OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let path = file.as_ref().to_owned();
+
+	match kind {
+		Similarity::Images => {
+			let image = image::open(&path).map_err(OrganiseError::FailedToDecodeImage)?;
+			let pixels = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+			let details = format!("{}x{}", image.width(), image.height());
+
+			Ok((SimilarFile { path, details: Some(details) }, Fingerprint::Image(difference_hash(pixels.as_raw()))))
+		}
+		Similarity::Videos => {
+			let probe = probe(&path, "v:0", "stream=width,height:format=duration,bit_rate")?;
+			let duration = probe.number("duration").ok_or_else(|| OrganiseError::FailedToProbeMedia(io::Error::other("missing duration")))?;
+			let frames = FRAME_POSITIONS.iter().map(|p| extract_frame(&path, duration * p)).collect::<Result<Vec<u64>, OrganiseError>>()?;
+
+			let details = format!(
+				"{}x{}, {} kb/s",
+				probe.number("width").unwrap_or_default(),
+				probe.number("height").unwrap_or_default(),
+				probe.number("bit_rate").unwrap_or_default() as u64 / 1000
+			);
+
+			Ok((SimilarFile { path, details: Some(details) }, Fingerprint::Video(frames, duration)))
+		}
+		Similarity::Audio => {
+			let (fingerprint, duration) = acoustic_fingerprint(&path)?;
+
+			// The quality of the recording is only informational, so the absence of ffprobe is not fatal.
+
+			let details = probe(&path, "a:0", "stream=codec_name,sample_rate:format=bit_rate").ok().map(|p| {
+				format!(
+					"{}, {} Hz, {} kb/s",
+					p.text("codec_name").unwrap_or("unknown"),
+					p.number("sample_rate").unwrap_or_default(),
+					p.number("bit_rate").unwrap_or_default() as u64 / 1000
+				)
+			});
+
+			Ok((SimilarFile { path, details }, Fingerprint::Audio(fingerprint, duration)))
+		}
+		Similarity::Documents => {
+			let contents = fs::read(&path).map_err(OrganiseError::FailedToReadFile)?;
+			let text = String::from_utf8_lossy(&contents);
+			let words: Vec<String> = text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(|w| w.to_lowercase()).collect();
+			let details = format!("{} words", words.len());
+
+			Ok((SimilarFile { path, details: Some(details) }, Fingerprint::Document(similarity_hash(&words))))
+		}
+	}
+}
+
+/// Computes the difference hash of the specified 9x8 greyscale pixels, which is resilient to re-encoding, resizing and changes to metadata.
+fn difference_hash(pixels: &[u8]) -> u64 {
+	let mut hash = 0u64;
+
+	for row in pixels.chunks_exact(9) {
+		for x in 0..8 {
+			hash = (hash << 1) | u64::from(row[x] < row[x + 1]);
+		}
+	}
+
+	hash
+}
+
+/// Computes the similarity hash of the specified words, whereby documents sharing most of their phrases have hashes differing in few bits.
+fn similarity_hash(words: &[String]) -> u64 {
+	let mut weights = [0i64; 64];
+
+	for shingle in words.windows(SHINGLE_LENGTH.min(words.len()).max(1)) {
+		let mut hasher = DefaultHasher::new();
+
+		shingle.hash(&mut hasher);
+
+		let feature = hasher.finish();
+
+		for (bit, weight) in weights.iter_mut().enumerate() {
+			*weight += if feature & (1 << bit) != 0 { 1 } else { -1 };
+		}
+	}
+
+	weights.iter().enumerate().filter(|(_, w)| **w > 0).fold(0, |h, (bit, _)| h | (1 << bit))
+}
+
+/// Represents the properties of a particular media file, as reported by `ffprobe`.
+struct Probe(Vec<(String, String)>);
+
+impl Probe {
+	/// Returns the textual value of the specified property, if present.
+	fn text(&self, key: &str) -> Option<&str> {
+		self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+	}
+
+	/// Returns the numeric value of the specified property, if present.
+	fn number(&self, key: &str) -> Option<f64> {
+		self.text(key).and_then(|v| v.parse().ok())
+	}
+}
+
+/// Probes the specified stream of the specified media file for the specified entries using `ffprobe`.
+fn probe<T>(file: T, stream: &str, entries: &str) -> Result<Probe, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let output = run(Command::new("ffprobe")
+		.args(["-v", "error", "-select_streams", stream])
+		.args(["-show_entries", entries])
+		.args(["-of", "default=noprint_wrappers=1"])
+		.arg(file.as_ref()))?;
+
+	let properties = String::from_utf8_lossy(&output)
+		.lines()
+		.filter_map(|l| l.split_once('='))
+		.map(|(k, v)| (k.trim().to_owned(), v.trim().to_owned()))
+		.collect();
+
+	Ok(Probe(properties))
+}
+
+/// Extracts the nearest keyframe at the specified position (in seconds) of the specified video and computes its difference hash using `ffmpeg`.
+fn extract_frame<T>(file: T, position: f64) -> Result<u64, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let output = run(Command::new("ffmpeg")
+		.args(["-v", "error", "-ss", &format!("{:.3}", position), "-i"])
+		.arg(file.as_ref())
+		.args(["-frames:v", "1", "-vf", "scale=9:8,format=gray", "-f", "rawvideo", "-"]))?;
+
+	if output.len() != 9 * 8 {
+		return Err(OrganiseError::FailedToProbeMedia(io::Error::other("unexpected frame size")));
+	}
+
+	Ok(difference_hash(&output))
+}
+
+/// Computes the acoustic fingerprint and duration (in seconds) of the specified recording using `fpcalc`.
+fn acoustic_fingerprint<T>(file: T) -> Result<(Vec<u32>, f64), OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let output = run(Command::new("fpcalc").arg("-raw").arg(file.as_ref()))?;
+	let output = String::from_utf8_lossy(&output);
+
+	let mut fingerprint = None;
+	let mut duration = None;
+
+	for (key, value) in output.lines().filter_map(|l| l.split_once('=')) {
+		match key {
+			"DURATION" => duration = value.trim().parse().ok(),
+			"FINGERPRINT" => fingerprint = value.trim().split(',').map(|v| v.parse().ok()).collect(),
+			_ => (),
+		}
+	}
+
+	fingerprint.zip(duration).ok_or_else(|| OrganiseError::FailedToProbeMedia(io::Error::other("missing fingerprint")))
+}
+
+/// Returns the proportion of matching bits between the specified acoustic fingerprints at their best alignment.
+fn acoustic_similarity(a: &[u32], b: &[u32]) -> f64 {
+	(-ALIGNMENT_OFFSET..=ALIGNMENT_OFFSET)
+		.filter_map(|offset| {
+			let (a, b) = if offset < 0 { (a.get((-offset) as usize..)?, b) } else { (a, b.get(offset as usize..)?) };
+			let length = a.len().min(b.len());
+
+			if length == 0 {
+				return None;
+			}
+
+			let errors: u32 = a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum();
+
+			Some(1.0 - f64::from(errors) / (length as f64 * 32.0))
+		})
+		.fold(0.0, f64::max)
+}
+
+/// Runs the specified command to completion, returning its standard output.
+fn run(command: &mut Command) -> Result<Vec<u8>, OrganiseError> {
+	let output = command.output().map_err(OrganiseError::FailedToProbeMedia)?;
+
+	if !output.status.success() {
+		let message = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+
+		return Err(OrganiseError::FailedToProbeMedia(io::Error::other(message)));
+	}
+
+	Ok(output.stdout)
+}
+
+/// Finds the root of the specified element within the specified disjoint set.
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+	while parents[i] != i {
+		parents[i] = parents[parents[i]];
+		i = parents[i];
+	}
+
+	i
+}
@@ -0,0 +1,139 @@
+use std::{
+	fs, io,
+	path::Path,
+	sync::{Condvar, Mutex},
+};
+
+use crate::session;
+
+/// Represents a limit upon the number of files whose contents are read at once.
+#[derive(Debug)]
+pub struct Gate {
+	/// The most files read at once.
+	limit: usize,
+
+	/// The number of files being read.
+	active: Mutex<usize>,
+
+	/// Notified whenever a file has been read.
+	released: Condvar,
+}
+
+/// Limits the number of files whose contents are read at once to the specified number, for the remainder of the current session.
+pub fn limit(limit: usize) {
+	let _ = session::current().gate.set(Gate {
+		limit: limit.max(1),
+		active: Mutex::new(0),
+		released: Condvar::new(),
+	});
+}
+
+/// Performs the specified read of the contents of a file once the limit upon files read at once allows.
+pub fn gated<R, F>(read: F) -> R
+where
+	F: FnOnce() -> R,
+{
+	let session = session::current();
+
+	let Some(gate) = session.gate.get() else {
+		return read();
+	};
+
+	let mut active = gate.released.wait_while(gate.active.lock().unwrap(), |a| *a >= gate.limit).unwrap();
+
+	*active += 1;
+	drop(active);
+
+	let result = read();
+
+	*gate.active.lock().unwrap() -= 1;
+	gate.released.notify_one();
+
+	result
+}
+
+/// Reads the entire contents of the specified file once the limit upon files read at once allows.
+pub fn read<T>(file: T) -> io::Result<Vec<u8>>
+where
+	T: AsRef<Path>,
+{
+	gated(|| fs::read(file))
+}
+
+/// Returns whether or not the specified directory resides upon rotational storage (such as a hard drive), should it be known.
+pub fn rotational<T>(dir: T) -> Option<bool>
+where
+	T: AsRef<Path>,
+{
+	#[cfg(target_os = "linux")]
+	{
+		use std::os::unix::fs::MetadataExt;
+
+		let device = dir.as_ref().metadata().ok()?.dev();
+		let device = fs::canonicalize(format!("/sys/dev/block/{}:{}", libc::major(device), libc::minor(device))).ok()?;
+
+		// Partitions have no queue of their own, but share that of the disk holding them.
+
+		let queue = [device.join("queue"), device.parent()?.join("queue")].into_iter().find_map(|q| fs::read_to_string(q.join("rotational")).ok())?;
+
+		Some(queue.trim() == "1")
+	}
+
+	#[cfg(windows)]
+	{
+		use std::{
+			os::windows::{fs::OpenOptionsExt, io::AsRawHandle},
+			path::{Component, Prefix},
+		};
+
+		use windows::Win32::{
+			Foundation::HANDLE,
+			System::{
+				Ioctl::{PropertyStandardQuery, StorageDeviceSeekPenaltyProperty, DEVICE_SEEK_PENALTY_DESCRIPTOR, IOCTL_STORAGE_QUERY_PROPERTY, STORAGE_PROPERTY_QUERY},
+				IO::DeviceIoControl,
+			},
+		};
+
+		// Only local drives can be asked whether they incur a penalty when seeking, which is how Windows itself tells hard drives apart.
+
+		let letter = match std::path::absolute(dir).ok()?.components().next()? {
+			Component::Prefix(p) => match p.kind() {
+				Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => char::from(letter),
+				_ => return None,
+			},
+			_ => return None,
+		};
+
+		let volume = fs::OpenOptions::new().access_mode(0).open(format!(r"\\.\{}:", letter)).ok()?;
+
+		let query = STORAGE_PROPERTY_QUERY {
+			PropertyId: StorageDeviceSeekPenaltyProperty,
+			QueryType: PropertyStandardQuery,
+			..Default::default()
+		};
+		let mut penalty = DEVICE_SEEK_PENALTY_DESCRIPTOR::default();
+
+		unsafe {
+			DeviceIoControl(
+				HANDLE(volume.as_raw_handle()),
+				IOCTL_STORAGE_QUERY_PROPERTY,
+				Some(&query as *const _ as *const _),
+				size_of::<STORAGE_PROPERTY_QUERY>() as u32,
+				Some(&mut penalty as *mut _ as *mut _),
+				size_of::<DEVICE_SEEK_PENALTY_DESCRIPTOR>() as u32,
+				None,
+				None,
+			)
+		}
+		.ok()?;
+
+		Some(penalty.IncursSeekPenalty)
+	}
+
+	#[cfg(not(any(target_os = "linux", windows)))]
+	{
+		let _ = dir;
+
+		None
+	}
+}
@@ -0,0 +1,495 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs::{self, File},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+
+use same_file::Handle;
+
+use crate::{
+	annex, attributes,
+	output::{say, warning},
+	paths, OrganiseError,
+};
+
+/// The name of the file within a store listing every reference to its content.
+const INDEX_FILE: &str = "index";
+
+/// The name of the folder within a store holding its content.
+const OBJECTS_FOLDER: &str = "objects";
+
+/// Represents a content-addressable store, where content lives once under its checksum and is referenced by links.
+#[derive(Debug)]
+pub struct Store {
+	/// The root directory of the store.
+	root: PathBuf,
+
+	/// The index of references, to which new references are appended.
+	index: Mutex<File>,
+
+	/// How the security contexts of content moved into the store are handled, if at all.
+	#[cfg(all(target_os = "linux", feature = "selinux"))]
+	labelling: Option<attributes::Labelling>,
+}
+
+/// Represents the outcome of collecting garbage from a store.
+#[derive(Debug, Clone)]
+pub struct Collection {
+	/// The number of references which remain.
+	pub references: usize,
+
+	/// The content removed as it was no longer referenced, alongside its size in bytes.
+	pub removed: Vec<(PathBuf, u64)>,
+}
+
+impl Store {
+	/// Opens the store at the specified directory, creating it should it not exist.
+	pub fn open<T>(root: T) -> Result<Self, OrganiseError>
+	where
+		T: AsRef<Path>,
+	{
+		fs::create_dir_all(root.as_ref().join(OBJECTS_FOLDER)).map_err(OrganiseError::FailedToCreateFolder)?;
+
+		let root = fs::canonicalize(root).map(paths::simplify).map_err(OrganiseError::FailedToReadFile)?;
+		let index = File::options().create(true).append(true).open(root.join(INDEX_FILE)).map_err(OrganiseError::FailedToUpdateStore)?;
+
+		Ok(Self {
+			root,
+			index: Mutex::new(index),
+			#[cfg(all(target_os = "linux", feature = "selinux"))]
+			labelling: None,
+		})
+	}
+
+	/// Specifies how the security contexts of content moved into this store are handled.
+	#[cfg(all(target_os = "linux", feature = "selinux"))]
+	pub fn labelled(self, labelling: Option<attributes::Labelling>) -> Self {
+		Self { labelling, ..self }
+	}
+
+	/// Returns the path at which the content of the specified (organised) file lives within this store.
+	fn object<T>(&self, file: T) -> PathBuf
+	where
+		T: AsRef<Path>,
+	{
+		let name = file.as_ref().file_name().and_then(|n| n.to_str()).unwrap_or_default();
+		let checksum = annex::parse(name).map(|k| k.checksum).unwrap_or_else(|| name.to_owned());
+
+		// Objects are spread across folders by the start of their checksum, to keep any one folder from growing too large.
+
+		self.root.join(OBJECTS_FOLDER).join(checksum.get(..2).unwrap_or(&checksum)).join(name)
+	}
+
+	/// Moves the content of the specified (organised) file into this store, should it not already exist there, and replaces the file with a link to it, recording the original name of the file should it have just been organised.
+	pub fn adopt<T>(&self, file: T, original: Option<&Path>) -> Result<(), OrganiseError>
+	where
+		T: AsRef<Path>,
+	{
+		let file = file.as_ref();
+		let object = self.object(file);
+
+		if object.try_exists().map_err(OrganiseError::FailedToReadFile)? {
+			// Content stored before it was made read-only is sealed once it is next found.
+
+			if is_same(file, &object) {
+				return seal(&object, true).map_err(OrganiseError::FailedToUpdateStore);
+			}
+
+			say!("Linking file <{}> to stored content <{}>...", file.display(), object.display());
+
+			// Attributes can only be written to content which is writable, so it is unsealed whilst they are preserved.
+
+			let _ = seal(&object, false);
+
+			attributes::preserve(file, &object);
+
+			fs::remove_file(file).map_err(OrganiseError::FailedToRemoveDuplicateFile)?;
+		} else {
+			say!("Storing file <{}> as <{}>...", file.display(), object.display());
+
+			if let Some(parent) = object.parent() {
+				fs::create_dir_all(parent).map_err(OrganiseError::FailedToCreateFolder)?;
+			}
+
+			#[cfg(all(target_os = "linux", feature = "selinux"))]
+			let context = attributes::context(file);
+
+			relocate(file, &object).map_err(OrganiseError::FailedToUpdateStore)?;
+
+			// Content moved within a file system keeps its context whereas copied content takes on the default of the store, either of which may be wrong for its readers.
+
+			#[cfg(all(target_os = "linux", feature = "selinux"))]
+			if let Some(labelling) = self.labelling {
+				attributes::label(&object, labelling, context.as_deref());
+			}
+		}
+
+		// Content is shared by every file linked to it, so it is made read-only, lest editing one file in place change every other.
+
+		if let Err(e) = seal(&object, true) {
+			warning!("Failed to make stored content <{}> read-only: {}.", object.display(), e);
+		}
+
+		// The file is restored from the store should it not be possible to link it, so that it is never lost.
+
+		if let Err(e) = link(&object, file) {
+			let _ = fs::copy(&object, file);
+
+			return Err(OrganiseError::FailedToUpdateStore(e));
+		}
+
+		self.reference(&object, file, original)
+	}
+
+	/// Records a reference from the specified file (and its original name, if known) to the specified object within the index of this store.
+	fn reference(&self, object: &Path, file: &Path, original: Option<&Path>) -> Result<(), OrganiseError> {
+		let file = file.parent().and_then(|p| fs::canonicalize(p).ok()).zip(file.file_name()).map(|(p, n)| paths::simplify(p.join(n))).unwrap_or_else(|| file.to_owned());
+		let object = object.strip_prefix(&self.root).unwrap_or(object);
+		let original = original.and_then(|o| o.file_name()).map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+		let line = format!("{}\t{}\t{}\n", object.display(), file.display(), original);
+
+		let mut index = self.index.lock().unwrap();
+
+		index.write_all(line.as_bytes()).map_err(OrganiseError::FailedToUpdateStore)
+	}
+
+	/// Removes the content of this store which is no longer referenced by any file, rewriting the index to only contain the references which remain.
+	pub fn collect(&self) -> Result<Collection, OrganiseError> {
+		let index = fs::read_to_string(self.root.join(INDEX_FILE)).map_err(OrganiseError::FailedToReadFile)?;
+		let mut references = Vec::new();
+		let mut dead = Vec::new();
+		let mut seen = HashSet::new();
+
+		// References are only live whilst the file still exists and is still a link to the same object, as it may since have been replaced or edited.
+
+		for line in index.lines() {
+			let mut columns = line.splitn(3, '\t');
+
+			let (Some(object), Some(file)) = (columns.next(), columns.next()) else {
+				continue;
+			};
+
+			let reference = (object, file.to_owned(), columns.next().unwrap_or_default());
+
+			match is_same(file, self.root.join(object)) {
+				true if seen.insert(file.to_owned()) => references.push(reference),
+				true => (),
+				false => dead.push(reference),
+			}
+		}
+
+		// Symbolic links may since have been renamed (or moved into another folder alongside them), so any still pointing at the content of a reference which is no longer live take its place.
+
+		let mut moved = HashMap::new();
+
+		for (object, file, original) in dead {
+			let object_path = self.root.join(object);
+
+			let relocated = Path::new(&file).parent().and_then(|folder| {
+				moved
+					.entry(folder.to_owned())
+					.or_insert_with(|| symlinks(folder))
+					.iter()
+					.find(|(link, target)| *target == object_path && !seen.contains(link.to_string_lossy().as_ref()))
+					.map(|(link, _)| link.to_string_lossy().into_owned())
+			});
+
+			// Content still hard linked elsewhere is referenced by a file which has since been moved, which keeps its reference rather than losing the content it shares.
+
+			match relocated {
+				Some(link) if seen.insert(link.clone()) => references.push((object, link, original)),
+				_ if is_linked(&object_path) && seen.insert(file.clone()) => references.push((object, file, original)),
+				_ => (),
+			}
+		}
+
+		let live: HashSet<PathBuf> = references.iter().map(|(object, _, _)| self.root.join(object)).collect();
+		let mut removed = Vec::new();
+
+		for folder in fs::read_dir(self.root.join(OBJECTS_FOLDER)).map_err(OrganiseError::FailedToListDirectory)?.flatten().filter(|f| f.path().is_dir()) {
+			for object in fs::read_dir(folder.path()).map_err(OrganiseError::FailedToListDirectory)?.flatten() {
+				let object = object.path();
+
+				if live.contains(&object) {
+					continue;
+				}
+
+				say!("Removing unreferenced content <{}>...", object.display());
+
+				let size = object.metadata().map(|m| m.len()).unwrap_or_default();
+
+				// Read-only files cannot be removed upon Windows, whereas elsewhere only the folder holding them needs to be writable.
+
+				#[cfg(windows)]
+				let _ = seal(&object, false);
+
+				fs::remove_file(&object).map_err(OrganiseError::FailedToRemoveDuplicateFile)?;
+				removed.push((object, size));
+			}
+
+			let _ = fs::remove_dir(folder.path());
+		}
+
+		let contents: String = references.iter().map(|(object, file, original)| format!("{}\t{}\t{}\n", object, file, original)).collect();
+		let mut index = self.index.lock().unwrap();
+
+		fs::write(self.root.join(INDEX_FILE), contents).map_err(OrganiseError::FailedToUpdateStore)?;
+		*index = File::options().append(true).open(self.root.join(INDEX_FILE)).map_err(OrganiseError::FailedToUpdateStore)?;
+
+		Ok(Collection { references: references.len(), removed })
+	}
+}
+
+/// Reads the references within the index of the store at the specified directory (without opening it for changes), as the object and the original name of each file, where only the latest reference from each file is kept.
+#[cfg(all(feature = "fuse", any(target_os = "linux", target_os = "macos")))]
+pub fn references<T>(root: T) -> Result<Vec<(PathBuf, PathBuf)>, OrganiseError>
+where
+	T: AsRef<Path>,
+{
+	let index = fs::read_to_string(root.as_ref().join(INDEX_FILE)).map_err(OrganiseError::FailedToReadFile)?;
+	let mut latest: std::collections::BTreeMap<&str, (&str, &str)> = std::collections::BTreeMap::new();
+
+	// Files which were already organised when referenced have no original name of their own, so keep any recorded before.
+
+	for line in index.lines() {
+		let mut columns = line.splitn(3, '\t');
+
+		let (Some(object), Some(file)) = (columns.next(), columns.next()) else {
+			continue;
+		};
+
+		let original = columns.next().filter(|o| !o.is_empty()).or_else(|| latest.get(file).map(|(_, o)| *o).filter(|o| !o.is_empty())).unwrap_or_default();
+
+		latest.insert(file, (object, original));
+	}
+
+	Ok(latest
+		.into_iter()
+		.map(|(file, (object, original))| {
+			let name = match original {
+				"" => Path::new(file).file_name().map(PathBuf::from).unwrap_or_default(),
+				original => PathBuf::from(original),
+			};
+
+			(root.as_ref().join(object), name)
+		})
+		.collect())
+}
+
+/// Returns whether or not the specified paths refer to the same underlying file (such as through a hard or symbolic link).
+fn is_same<T, U>(a: T, b: U) -> bool
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	matches!((Handle::from_path(a), Handle::from_path(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Returns every symbolic link within the specified folder, alongside the (canonical) path it points to.
+fn symlinks(folder: &Path) -> Vec<(PathBuf, PathBuf)> {
+	let Ok(entries) = fs::read_dir(folder) else {
+		return Vec::new();
+	};
+
+	entries.flatten().filter(|e| e.file_type().is_ok_and(|t| t.is_symlink())).filter_map(|e| fs::canonicalize(e.path()).map(paths::simplify).ok().map(|t| (e.path(), t))).collect()
+}
+
+/// Returns whether or not the specified object is hard linked to by any file, which is always the case whilst a file sharing it exists (wherever it has since been moved to within the same file system).
+fn is_linked(object: &Path) -> bool {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::MetadataExt;
+
+		object.metadata().is_ok_and(|m| m.nlink() > 1)
+	}
+
+	#[cfg(not(unix))]
+	false
+}
+
+/// Moves the specified file to the specified path, copying it should the path reside on a different file system.
+pub fn relocate<T, U>(from: T, to: U) -> io::Result<()>
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	// Renames only fail as such between volumes, where not even APFS can clone content, so a plain copy is all that can be done. Every copy within a volume (such as restoring a file from the store) is cloned by the standard library where it can, which tries fclonefileat before falling back to fcopyfile on macOS, and copy_file_range (which reflinks on Btrfs and XFS) on Linux.
+
+	match fs::rename(&from, &to) {
+		Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+			fs::copy(&from, &to).inspect_err(|_| {
+				let _ = fs::remove_file(&to);
+			})?;
+
+			attributes::carry(&from, &to);
+
+			fs::remove_file(from)
+		}
+		result => result,
+	}
+}
+
+/// Runs the specified operation upon the specified file whilst it is writable, should it be read-only (as is content within a store, and every file linked to it), making it read-only once again afterwards.
+pub fn unsealed<T, F, R>(file: T, operation: F) -> io::Result<R>
+where
+	T: AsRef<Path>,
+	F: FnOnce() -> io::Result<R>,
+{
+	let file = file.as_ref();
+	let sealed = fs::metadata(file)?.permissions().readonly();
+
+	if sealed {
+		seal(file, false)?;
+	}
+
+	let result = operation();
+
+	if sealed {
+		if let Err(e) = seal(file, true) {
+			warning!("Failed to make file <{}> read-only again: {}.", file.display(), e);
+		}
+	}
+
+	result
+}
+
+/// Makes the specified object read-only (or writable by its owner once again, should it not be sealed), along with every file linked to it.
+fn seal(object: &Path, sealed: bool) -> io::Result<()> {
+	let mut permissions = fs::metadata(object)?.permissions();
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+
+		let mode = permissions.mode();
+
+		permissions.set_mode(if sealed { mode & !0o222 } else { mode | 0o200 });
+	}
+
+	#[cfg(not(unix))]
+	permissions.set_readonly(sealed);
+
+	fs::set_permissions(object, permissions)
+}
+
+/// Creates a link at the specified path to the specified object, preferring a hard link and falling back to a symbolic link (such as across file systems).
+fn link(object: &Path, file: &Path) -> io::Result<()> {
+	match fs::hard_link(object, file) {
+		Ok(()) => Ok(()),
+
+		#[cfg(unix)]
+		Err(_) => std::os::unix::fs::symlink(object, file),
+
+		#[cfg(windows)]
+		Err(_) => std::os::windows::fs::symlink_file(object, file),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::testing::scratch;
+
+	/// Returns the (organised) name of a file with the specified contents.
+	fn organised(contents: &str) -> String {
+		format!("{:x}.txt", md5::compute(contents))
+	}
+
+	#[test]
+	fn adopts_content_once() {
+		let (dir, root) = (scratch("store-adopt"), scratch("store-adopt-store"));
+		let store = Store::open(&root).unwrap();
+		let (first, second) = (dir.join(organised("same")), dir.join("other").join(organised("same")));
+
+		fs::create_dir(dir.join("other")).unwrap();
+		fs::write(&first, "same").unwrap();
+		fs::write(&second, "same").unwrap();
+
+		store.adopt(&first, Some(Path::new("photo.txt"))).unwrap();
+		store.adopt(&second, None).unwrap();
+
+		// Adopting a file already linked to its content only seals it again.
+
+		store.adopt(&first, None).unwrap();
+
+		let object = store.object(organised("same"));
+
+		assert!(object.starts_with(root.join(OBJECTS_FOLDER).join(&organised("same")[..2])));
+		assert!(is_same(&first, &object) && is_same(&second, &object));
+		assert!(object.metadata().unwrap().permissions().readonly());
+		assert_eq!(fs::read_to_string(&second).unwrap(), "same");
+
+		let index = fs::read_to_string(root.join(INDEX_FILE)).unwrap();
+
+		assert_eq!(index.lines().count(), 2);
+		assert!(index.lines().next().unwrap().ends_with("\tphoto.txt"));
+
+		fs::remove_dir_all(&dir).unwrap();
+		fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn stores_annex_keys_by_their_checksum() {
+		let root = scratch("store-annex");
+		let store = Store::open(&root).unwrap();
+		let name = annex::key("d41d8cd98f00b204e9800998ecf8427e", 0, Some("jpg"));
+
+		assert_eq!(store.object(&name), root.join(OBJECTS_FOLDER).join("d4").join(&name));
+
+		fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn collects_only_unreferenced_content() {
+		let (dir, root) = (scratch("store-collect"), scratch("store-collect-store"));
+		let store = Store::open(&root).unwrap();
+
+		for contents in ["kept", "removed"] {
+			fs::write(dir.join(organised(contents)), contents).unwrap();
+			store.adopt(dir.join(organised(contents)), None).unwrap();
+		}
+
+		fs::remove_file(dir.join(organised("removed"))).unwrap();
+
+		let collection = store.collect().unwrap();
+
+		assert_eq!(collection.references, 1);
+		assert_eq!(collection.removed.len(), 1);
+		assert!(store.object(organised("kept")).exists());
+		assert!(!store.object(organised("removed")).exists());
+
+		fs::remove_dir_all(&dir).unwrap();
+		fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn keeps_content_of_moved_links() {
+		let (dir, root) = (scratch("store-moved"), scratch("store-moved-store"));
+		let store = Store::open(&root).unwrap();
+
+		for contents in ["hard", "symbolic"] {
+			fs::write(dir.join(organised(contents)), contents).unwrap();
+			store.adopt(dir.join(organised(contents)), None).unwrap();
+		}
+
+		// A hard link moved anywhere still shares the content, whereas a symbolic link renamed alongside its reference is found in its place.
+
+		fs::create_dir(dir.join("elsewhere")).unwrap();
+		fs::rename(dir.join(organised("hard")), dir.join("elsewhere").join("hard.txt")).unwrap();
+		fs::remove_file(dir.join(organised("symbolic"))).unwrap();
+		std::os::unix::fs::symlink(store.object(organised("symbolic")), dir.join("symbolic.txt")).unwrap();
+
+		let collection = store.collect().unwrap();
+
+		assert!(collection.removed.is_empty());
+		assert_eq!(fs::read_to_string(dir.join("elsewhere").join("hard.txt")).unwrap(), "hard");
+		assert_eq!(fs::read_to_string(dir.join("symbolic.txt")).unwrap(), "symbolic");
+		assert!(fs::read_to_string(root.join(INDEX_FILE)).unwrap().contains("symbolic.txt"));
+
+		fs::remove_dir_all(&dir).unwrap();
+		fs::remove_dir_all(&root).unwrap();
+	}
+}
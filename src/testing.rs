@@ -0,0 +1,11 @@
+use std::{env, fs, path::PathBuf, process};
+
+/// Creates an empty directory of the specified name for a test to create files within.
+pub fn scratch(name: &str) -> PathBuf {
+	let dir = env::temp_dir().join(format!("directory-organiser-{}-{}", name, process::id()));
+
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+
+	dir
+}
@@ -0,0 +1,255 @@
+use std::{
+	collections::{BTreeMap, HashSet},
+	fs::{self, File},
+	io::{self, Read},
+	path::{Path, PathBuf},
+};
+
+use sha1::{Digest, Sha1};
+
+use crate::{output::warning, OrganiseError};
+
+/// The length of each SHA-1 piece hash within a torrent, in bytes.
+const PIECE_HASH_LENGTH: usize = 20;
+
+/// Represents a bencoded value, as used by .torrent files.
+#[derive(Debug, Clone)]
+enum Value {
+	/// Indicates an integer.
+	Integer(i64),
+
+	/// Indicates a byte string.
+	Bytes(Vec<u8>),
+
+	/// Indicates a list of values.
+	List(Vec<Value>),
+
+	/// Indicates a dictionary of values, keyed by byte strings.
+	Dictionary(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+	/// Returns the value of the specified key, if this value is a dictionary containing it.
+	fn get(&self, key: &str) -> Option<&Value> {
+		match self {
+			Self::Dictionary(entries) => entries.get(key.as_bytes()),
+			_ => None,
+		}
+	}
+
+	/// Returns this value as an integer, if it is one.
+	fn integer(&self) -> Option<i64> {
+		match self {
+			Self::Integer(i) => Some(*i),
+			_ => None,
+		}
+	}
+
+	/// Returns this value as a byte string, if it is one.
+	fn bytes(&self) -> Option<&[u8]> {
+		match self {
+			Self::Bytes(b) => Some(b),
+			_ => None,
+		}
+	}
+
+	/// Returns this value as a list, if it is one.
+	fn list(&self) -> Option<&[Value]> {
+		match self {
+			Self::List(l) => Some(l),
+			_ => None,
+		}
+	}
+}
+
+/// Represents a torrent, describing the files it seeds from a particular directory.
+#[derive(Debug, Clone)]
+pub struct Torrent {
+	/// The path of the .torrent file.
+	pub path: PathBuf,
+
+	/// The paths and lengths of the files seeded by the torrent, in the order their contents are hashed.
+	pub files: Vec<(PathBuf, u64)>,
+
+	/// The length of each piece, in bytes.
+	pub piece_length: u64,
+
+	/// The SHA-1 hash of each piece.
+	pub pieces: Vec<[u8; PIECE_HASH_LENGTH]>,
+}
+
+/// Represents the outcome of verifying the files seeded by a torrent against its piece hashes.
+#[derive(Debug, Clone)]
+pub struct Verification {
+	/// The number of pieces whose contents match their hash.
+	pub matched: usize,
+
+	/// The total number of pieces.
+	pub total: usize,
+
+	/// The seeded files which do not exist.
+	pub missing: Vec<PathBuf>,
+}
+
+impl Torrent {
+	/// Reads the specified .torrent file, resolving the files it seeds relative to the specified directory.
+	pub fn open<T, U>(file: T, dir: U) -> Result<Self, OrganiseError>
+	where
+		T: AsRef<Path>,
+		U: AsRef<Path>,
+	{
+		let contents = fs::read(&file).map_err(OrganiseError::FailedToReadFile)?;
+		let invalid = |reason: &str| OrganiseError::FailedToReadTorrent(io::Error::new(io::ErrorKind::InvalidData, reason.to_owned()));
+
+		let (root, _) = decode(&contents, 0).ok_or_else(|| invalid("malformed bencoding"))?;
+		let info = root.get("info").ok_or_else(|| invalid("missing info dictionary"))?;
+
+		let name = info.get("name").and_then(Value::bytes).map(sanitise).ok_or_else(|| invalid("missing name"))?;
+		let piece_length = info.get("piece length").and_then(Value::integer).filter(|l| *l > 0).ok_or_else(|| invalid("missing piece length"))? as u64;
+		let pieces = info.get("pieces").and_then(Value::bytes).filter(|p| p.len() % PIECE_HASH_LENGTH == 0).ok_or_else(|| invalid("missing pieces"))?;
+
+		// Torrents either seed a single file, or a folder of files listed by their path components.
+
+		let files = match info.get("files").and_then(Value::list) {
+			Some(files) => files
+				.iter()
+				.map(|f| {
+					let length = f.get("length").and_then(Value::integer).ok_or_else(|| invalid("missing file length"))? as u64;
+					let path = f.get("path").and_then(Value::list).ok_or_else(|| invalid("missing file path"))?;
+					let path = path.iter().filter_map(Value::bytes).map(sanitise).fold(dir.as_ref().join(&name), |p, c| p.join(c));
+
+					Ok((path, length))
+				})
+				.collect::<Result<Vec<(PathBuf, u64)>, OrganiseError>>()?,
+			None => vec![(dir.as_ref().join(&name), info.get("length").and_then(Value::integer).ok_or_else(|| invalid("missing length"))? as u64)],
+		};
+
+		Ok(Self {
+			path: file.as_ref().to_owned(),
+			files,
+			piece_length,
+			pieces: pieces.chunks_exact(PIECE_HASH_LENGTH).map(|c| c.try_into().unwrap()).collect(),
+		})
+	}
+
+	/// Verifies the contents of the seeded files against the piece hashes of this torrent.
+	pub fn verify(&self) -> Result<Verification, OrganiseError> {
+		let mut buffer = Vec::with_capacity(self.piece_length as usize);
+		let mut missing = Vec::new();
+		let mut hashes = Vec::with_capacity(self.pieces.len());
+
+		// Pieces span file boundaries, so the files are read as one continuous stream (with missing files read as zeroes, which cannot match).
+
+		for (path, length) in &self.files {
+			let mut remaining = *length;
+			let mut reader: Box<dyn Read> = match File::open(path) {
+				Ok(handle) => Box::new(handle.take(*length).chain(io::repeat(0)).take(*length)),
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {
+					missing.push(path.to_owned());
+
+					Box::new(io::repeat(0).take(*length))
+				}
+				Err(e) => return Err(OrganiseError::FailedToReadFile(e)),
+			};
+
+			while remaining > 0 {
+				let wanted = (self.piece_length - buffer.len() as u64).min(remaining);
+				let start = buffer.len();
+
+				buffer.resize(start + wanted as usize, 0);
+				reader.read_exact(&mut buffer[start..]).map_err(OrganiseError::FailedToReadFile)?;
+				remaining -= wanted;
+
+				if buffer.len() as u64 == self.piece_length {
+					hashes.push(Sha1::digest(&buffer));
+					buffer.clear();
+				}
+			}
+		}
+
+		if !buffer.is_empty() {
+			hashes.push(Sha1::digest(&buffer));
+		}
+
+		Ok(Verification {
+			matched: hashes.iter().zip(&self.pieces).filter(|(actual, expected)| actual.as_slice() == expected.as_slice()).count(),
+			total: self.pieces.len(),
+			missing,
+		})
+	}
+}
+
+/// Reads every .torrent file amongst the specified files, resolving the files they seed relative to the specified directory.
+pub fn find_torrents<T>(files: &[PathBuf], dir: T) -> Vec<Torrent>
+where
+	T: AsRef<Path>,
+{
+	files
+		.iter()
+		.filter(|f| f.extension().is_some_and(|e| e.eq_ignore_ascii_case("torrent")))
+		.filter_map(|f| Torrent::open(f, &dir).map_err(|e| warning!("Failed to read torrent <{}>: {}.", f.display(), e)).ok())
+		.collect()
+}
+
+/// Returns the paths of every file seeded by the specified torrents.
+pub fn seeded(torrents: &[Torrent]) -> HashSet<PathBuf> {
+	torrents.iter().flat_map(|t| t.files.iter().map(|(p, _)| p.to_owned())).collect()
+}
+
+/// Converts the specified name or path component of a torrent into one which cannot escape the directory it is resolved within.
+fn sanitise(component: &[u8]) -> String {
+	match String::from_utf8_lossy(component).as_ref() {
+		"" | "." | ".." => "_".to_owned(),
+		component => component.replace(['/', '\\'], "_"),
+	}
+}
+
+/// Decodes the bencoded value beginning at the specified offset, alongside the offset following it.
+fn decode(data: &[u8], offset: usize) -> Option<(Value, usize)> {
+	match *data.get(offset)? {
+		b'i' => {
+			let end = offset + data[offset..].iter().position(|b| *b == b'e')?;
+			let integer = std::str::from_utf8(&data[offset + 1..end]).ok()?.parse().ok()?;
+
+			Some((Value::Integer(integer), end + 1))
+		}
+		b'l' => {
+			let mut items = Vec::new();
+			let mut offset = offset + 1;
+
+			while *data.get(offset)? != b'e' {
+				let (item, next) = decode(data, offset)?;
+
+				items.push(item);
+				offset = next;
+			}
+
+			Some((Value::List(items), offset + 1))
+		}
+		b'd' => {
+			let mut entries = BTreeMap::new();
+			let mut offset = offset + 1;
+
+			while *data.get(offset)? != b'e' {
+				let (Value::Bytes(key), next) = decode(data, offset)? else {
+					return None;
+				};
+
+				let (value, next) = decode(data, next)?;
+
+				entries.insert(key, value);
+				offset = next;
+			}
+
+			Some((Value::Dictionary(entries), offset + 1))
+		}
+		b'0'..=b'9' => {
+			let colon = offset + data[offset..].iter().position(|b| *b == b':')?;
+			let length: usize = std::str::from_utf8(&data[offset..colon]).ok()?.parse().ok()?;
+			let end = (colon + 1).checked_add(length)?;
+
+			Some((Value::Bytes(data.get(colon + 1..end)?.to_vec()), end))
+		}
+		_ => None,
+	}
+}
@@ -0,0 +1,240 @@
+use std::{
+	collections::BTreeMap,
+	ffi::{OsStr, OsString},
+	fs::{self, File},
+	os::unix::fs::{FileExt, MetadataExt},
+	path::{Path, PathBuf},
+	time::{Duration, UNIX_EPOCH},
+};
+
+use fuser::{Config, Errno, FileAttr, FileHandle, FileType, Filesystem, Generation, INodeNo, LockOwner, MountOption, OpenAccMode, OpenFlags, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, ReplyOpen, Request};
+
+use crate::{dates, store, OrganiseError};
+
+/// How long the kernel may cache the attributes and entries of the view, which never change whilst mounted.
+const TTL: Duration = Duration::from_secs(60);
+
+/// The name of the folder holding content whose modification time is unknown.
+const UNKNOWN_DATE: &str = "Unknown Date";
+
+/// Represents a particular folder or file within the view.
+#[derive(Debug)]
+enum Node {
+	/// Indicates a folder, with its entries by name.
+	Folder(BTreeMap<OsString, INodeNo>),
+
+	/// Indicates a file, presenting the content of the specified object.
+	File(PathBuf),
+}
+
+/// Represents a read-only view of a store, presenting its content under the original names of the files which referenced it, grouped into year and month folders.
+#[derive(Debug)]
+pub struct View {
+	/// The folders and files of the view, where the inode of each is its position plus one (so that the root folder is first).
+	nodes: Vec<Node>,
+
+	/// The owner of the view, which is always that of the store.
+	owner: (u32, u32),
+}
+
+impl View {
+	/// Builds the view of the store at the specified directory from the references within its index.
+	pub fn open<T>(root: T) -> Result<Self, OrganiseError>
+	where
+		T: AsRef<Path>,
+	{
+		let owner = fs::metadata(&root).map(|m| (m.uid(), m.gid())).map_err(OrganiseError::FailedToReadFile)?;
+
+		let mut view = Self {
+			nodes: vec![Node::Folder(BTreeMap::new())],
+			owner,
+		};
+
+		// Content which no longer exists (such as after collecting garbage) is left out.
+
+		for (object, name) in store::references(root)? {
+			let (Ok(metadata), false) = (fs::metadata(&object), name.as_os_str().is_empty()) else {
+				continue;
+			};
+
+			let folders = match metadata.modified().ok().and_then(|m| m.duration_since(UNIX_EPOCH).ok()) {
+				Some(modified) => {
+					let (year, month, _) = dates::civil((modified.as_secs() / 86400) as i64);
+
+					vec![format!("{:04}", year), format!("{:02}", month)]
+				}
+				None => vec![UNKNOWN_DATE.to_owned()],
+			};
+
+			let folder = folders.iter().fold(INodeNo::ROOT, |parent, name| view.folder(parent, name.as_ref()));
+
+			view.file(folder, name.as_os_str(), object);
+		}
+
+		Ok(view)
+	}
+
+	/// Returns the folder with the specified name within the specified folder, adding it should it not yet exist.
+	fn folder(&mut self, parent: INodeNo, name: &OsStr) -> INodeNo {
+		if let Some(&ino) = self.entries(parent).and_then(|e| e.get(name)) {
+			return ino;
+		}
+
+		self.add(parent, name.to_owned(), Node::Folder(BTreeMap::new()))
+	}
+
+	/// Adds a file presenting the specified object with the specified name to the specified folder, numbering the name should it already be taken (such as by a file of the same name from elsewhere).
+	fn file(&mut self, parent: INodeNo, name: &OsStr, object: PathBuf) {
+		let path = Path::new(name);
+		let stem = path.file_stem().unwrap_or(name).to_string_lossy().into_owned();
+		let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+
+		let mut candidate = name.to_owned();
+
+		for n in 2.. {
+			if !self.entries(parent).is_some_and(|e| e.contains_key(&candidate)) {
+				break;
+			}
+
+			candidate = OsString::from(format!("{} ({}){}", stem, n, extension));
+		}
+
+		self.add(parent, candidate, Node::File(object));
+	}
+
+	/// Adds the specified node with the specified name to the specified folder, returning its inode.
+	fn add(&mut self, parent: INodeNo, name: OsString, node: Node) -> INodeNo {
+		self.nodes.push(node);
+
+		let ino = INodeNo(self.nodes.len() as u64);
+
+		if let Some(Node::Folder(entries)) = self.nodes.get_mut(index(parent)) {
+			entries.insert(name, ino);
+		}
+
+		ino
+	}
+
+	/// Returns the entries of the specified folder, or nothing should it not be a folder.
+	fn entries(&self, ino: INodeNo) -> Option<&BTreeMap<OsString, INodeNo>> {
+		match self.nodes.get(index(ino)) {
+			Some(Node::Folder(entries)) => Some(entries),
+			_ => None,
+		}
+	}
+
+	/// Returns the attributes of the specified node, taking those of files from the content they present.
+	fn attributes(&self, ino: INodeNo) -> Option<FileAttr> {
+		let (kind, perm, size, modified) = match self.nodes.get(index(ino))? {
+			Node::Folder(_) => (FileType::Directory, 0o555, 0, UNIX_EPOCH),
+			Node::File(object) => {
+				let metadata = fs::metadata(object).ok()?;
+
+				(FileType::RegularFile, 0o444, metadata.len(), metadata.modified().unwrap_or(UNIX_EPOCH))
+			}
+		};
+
+		Some(FileAttr {
+			ino,
+			size,
+			blocks: size.div_ceil(512),
+			atime: modified,
+			mtime: modified,
+			ctime: modified,
+			crtime: modified,
+			kind,
+			perm,
+			nlink: 1,
+			uid: self.owner.0,
+			gid: self.owner.1,
+			rdev: 0,
+			blksize: 4096,
+			flags: 0,
+		})
+	}
+}
+
+impl Filesystem for View {
+	fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+		match self.entries(parent).and_then(|e| e.get(name)).and_then(|&ino| self.attributes(ino)) {
+			Some(attributes) => reply.entry(&TTL, &attributes, Generation(0)),
+			None => reply.error(Errno::ENOENT),
+		}
+	}
+
+	fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+		match self.attributes(ino) {
+			Some(attributes) => reply.attr(&TTL, &attributes),
+			None => reply.error(Errno::ENOENT),
+		}
+	}
+
+	fn open(&self, _req: &Request, ino: INodeNo, flags: OpenFlags, reply: ReplyOpen) {
+		match self.nodes.get(index(ino)) {
+			Some(Node::File(_)) if flags.acc_mode() == OpenAccMode::O_RDONLY => reply.opened(FileHandle(0), fuser::FopenFlags::empty()),
+			Some(Node::File(_)) => reply.error(Errno::EROFS),
+			Some(Node::Folder(_)) => reply.error(Errno::EISDIR),
+			None => reply.error(Errno::ENOENT),
+		}
+	}
+
+	fn read(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, size: u32, _flags: OpenFlags, _lock_owner: Option<LockOwner>, reply: ReplyData) {
+		let Some(Node::File(object)) = self.nodes.get(index(ino)) else {
+			return reply.error(Errno::ENOENT);
+		};
+
+		let mut buffer = vec![0u8; size as usize];
+
+		match File::open(object).and_then(|f| f.read_at(&mut buffer, offset)) {
+			Ok(read) => reply.data(&buffer[..read]),
+			Err(e) => reply.error(Errno::from(e)),
+		}
+	}
+
+	fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
+		let Some(entries) = self.entries(ino) else {
+			return reply.error(Errno::ENOTDIR);
+		};
+
+		let entries = entries.iter().map(|(name, &ino)| {
+			let kind = match self.nodes.get(index(ino)) {
+				Some(Node::Folder(_)) => FileType::Directory,
+				_ => FileType::RegularFile,
+			};
+
+			(ino, kind, name.as_os_str())
+		});
+
+		// The parent of each folder is not tracked, which the kernel resolves itself.
+
+		let entries = [(ino, FileType::Directory, OsStr::new(".")), (ino, FileType::Directory, OsStr::new(".."))].into_iter().chain(entries);
+
+		for (i, (ino, kind, name)) in entries.enumerate().skip(offset as usize) {
+			if reply.add(ino, (i + 1) as u64, kind, name) {
+				break;
+			}
+		}
+
+		reply.ok();
+	}
+}
+
+/// Mounts a read-only view of the store at the specified directory onto the specified mount point, until it is unmounted.
+pub fn mount<T, U>(root: T, mountpoint: U) -> Result<(), OrganiseError>
+where
+	T: AsRef<Path>,
+	U: AsRef<Path>,
+{
+	let view = View::open(&root)?;
+
+	let mut config = Config::default();
+
+	config.mount_options = vec![MountOption::RO, MountOption::FSName(root.as_ref().display().to_string()), MountOption::Subtype("directory-organiser".to_owned())];
+
+	fuser::mount(view, mountpoint, &config).map_err(OrganiseError::FailedToMountStore)
+}
+
+/// Returns the position of the specified inode within the nodes of a view.
+fn index(ino: INodeNo) -> usize {
+	(u64::from(ino) as usize).wrapping_sub(1)
+}
@@ -0,0 +1,107 @@
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+	sync::mpsc,
+	time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::{cache::{self, Cache}, checksummed, checksums, journal, organise_folder, output::{say, warning}, store::Store, written_checksums, Args, OrganiseError, OrganiseResult};
+
+/// The longest to wait for a notification before checking whether any folder has settled.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches the specified directory (and the folders beneath it, when organising recursively) for files which are created or modified, organising the folder holding them once it has settled, until interrupted.
+///
+/// A folder has settled once no file within it has been created or modified for the settle time (or a second, should it be shorter), so that files still being written (such as downloads in progress) are not hashed until they are complete.
+pub fn watch(dir: &Path, store: Option<&Store>, cache: Option<&Cache>, args: &Args) -> OrganiseResult {
+	let (sender, receiver) = mpsc::channel();
+
+	let mut watcher = notify::recommended_watcher(sender).map_err(OrganiseError::FailedToWatchDirectory)?;
+	let mode = if args.recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+
+	watcher.watch(dir, mode).map_err(OrganiseError::FailedToWatchDirectory)?;
+
+	say!("Watching <{}> for new files...", dir.display());
+
+	let root = fs::canonicalize(dir).map_err(OrganiseError::FailedToListDirectory)?;
+	let excluded: Vec<PathBuf> = [args.store.as_deref(), args.quarantine.as_deref()].into_iter().flatten().filter_map(|e| fs::canonicalize(e).ok()).collect();
+	let settle = Duration::from_secs(args.settle_time).max(POLL_INTERVAL);
+
+	let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+	loop {
+		match receiver.recv_timeout(POLL_INTERVAL) {
+			Ok(Ok(event)) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+				for path in event.paths.iter().filter(|p| !ignored(p, args)) {
+					let Some(folder) = path.parent().and_then(|p| fs::canonicalize(p).ok()) else {
+						continue;
+					};
+
+					// Folders deeper than the depth organised (or excluded from organising, such as the quarantine) are never organised by watching either.
+
+					let Ok(relative) = folder.strip_prefix(&root) else {
+						continue;
+					};
+
+					if args.max_depth.is_some_and(|m| relative.components().count() > m) || excluded.iter().any(|e| folder.starts_with(e)) {
+						continue;
+					}
+
+					// Folders are organised by their paths beneath the directory as it was specified, as they are when first organised, so that their files are found within the cache (and reported) by the same paths.
+
+					let folder = match relative.as_os_str().is_empty() {
+						true => dir.to_owned(),
+						false => dir.join(relative),
+					};
+
+					pending.insert(folder, Instant::now());
+				}
+			}
+			Ok(Ok(_)) | Err(mpsc::RecvTimeoutError::Timeout) => (),
+			Ok(Err(e)) => warning!("Failed to watch <{}>: {}.", dir.display(), e),
+			Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+		}
+
+		let settled: Vec<PathBuf> = pending.iter().filter(|(_, changed)| changed.elapsed() >= settle).map(|(f, _)| f.clone()).collect();
+
+		if settled.is_empty() {
+			continue;
+		}
+
+		for folder in settled {
+			pending.remove(&folder);
+
+			if let Err(e) = organise_folder(&folder, store, cache, None, None, args) {
+				warning!("Failed to organise folder <{}>: {}.", folder.display(), e);
+			}
+		}
+
+		if let Some(algorithm) = written_checksums(args) {
+			if let Err(e) = checksums::write(dir, &checksummed(dir, args), algorithm, cache) {
+				warning!("Failed to write checksums: {}.", e);
+			}
+		}
+
+		if let Some(cache) = cache {
+			if let Err(e) = cache.write() {
+				warning!("Failed to write cache: {}.", e);
+			}
+		}
+
+		say!("Watching <{}> for new files...", dir.display());
+	}
+}
+
+/// Returns whether or not a notification concerning the specified file is ignored, as files which are already organised (such as by organising the folder) or written by organising itself need not be organised again, and those which are gone (such as those renamed as they were organised) or are folders have nothing to organise.
+fn ignored(file: &Path, args: &Args) -> bool {
+	if !file.is_file() {
+		return true;
+	}
+
+	let organised = file.file_stem().and_then(|n| n.to_str()).is_some_and(|n| args.algorithm.is_checksum(n));
+
+	organised || checksums::is_checksum_file(file) || journal::is_journal(file) || cache::is_index(file)
+}
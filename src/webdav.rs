@@ -0,0 +1,263 @@
+use std::{
+	env,
+	fmt::Write,
+	io::{self, Read},
+	thread,
+	time::Duration,
+};
+
+use regex::Regex;
+use ureq::{
+	http::{Request, Response},
+	Agent,
+};
+
+use crate::remote::{Backend, Entry};
+
+/// The environment variable holding the password to authenticate with, if any.
+const PASSWORD_VARIABLE: &str = "DIRECTORY_ORGANISER_WEBDAV_PASSWORD";
+
+/// The number of times a request is attempted before giving up.
+const ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry of a failed request, which doubles with each further retry.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// The time allowed to connect to the server, and then for it to begin responding to each request.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The properties requested when listing the directory (including the checksums kept by Nextcloud and ownCloud).
+const PROPERTIES: &str = r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns"><d:prop><d:resourcetype/><oc:checksums/></d:prop></d:propfind>"#;
+
+/// The characters used by Base64 encoding.
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Represents a remote directory accessed over WebDAV (such as a Nextcloud folder or a share on a NAS).
+pub struct WebDav {
+	/// The location of the directory, as specified.
+	location: String,
+
+	/// The URL of the directory, ending with a separator.
+	url: String,
+
+	/// The value of the authorisation header sent with each request, if any.
+	authorization: Option<String>,
+
+	/// The agent through which requests are made.
+	agent: Agent,
+}
+
+impl WebDav {
+	/// Opens the directory at the specified location (such as `davs://user@host/path` over HTTPS, or `dav://` over plain HTTP), authenticating with the password from the environment should a user be specified.
+	pub fn open(location: &str) -> io::Result<Self> {
+		let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidInput, reason.to_owned());
+
+		let (scheme, rest) = match location.split_once("://") {
+			Some(("davs", rest)) => ("https", rest),
+			Some(("dav", rest)) => ("http", rest),
+			_ => return Err(invalid("not a dav:// or davs:// location")),
+		};
+
+		let (authority, dir) = rest.split_once('/').unwrap_or((rest, ""));
+
+		let (user, host) = match authority.rsplit_once('@') {
+			Some((user, host)) => (Some(user), host),
+			None => (None, authority),
+		};
+
+		if host.is_empty() {
+			return Err(invalid("missing host"));
+		}
+
+		let authorization = match user {
+			Some(user) => {
+				let password = env::var(PASSWORD_VARIABLE).map_err(|_| invalid(&format!("missing {}", PASSWORD_VARIABLE)))?;
+
+				Some(format!("Basic {}", base64(format!("{}:{}", user, password).as_bytes())))
+			}
+			None => None,
+		};
+
+		let path: String = dir.split('/').filter(|s| !s.is_empty()).map(|s| format!("/{}", encode(s))).collect();
+
+		let agent = Agent::config_builder().allow_non_standard_methods(true).http_status_as_error(true).timeout_connect(Some(TIMEOUT)).timeout_recv_response(Some(TIMEOUT)).build().into();
+
+		Ok(Self {
+			location: location.trim_end_matches('/').to_owned(),
+			url: format!("{}://{}{}/", scheme, host, path),
+			authorization,
+			agent,
+		})
+	}
+
+	/// Performs a request with the specified method upon the specified file (or the directory itself, should it be empty), retrying should the server be busy or unreachable.
+	fn request(&self, method: &str, name: &str, headers: &[(&str, String)], body: &str) -> io::Result<Response<ureq::Body>> {
+		let url = format!("{}{}", self.url, encode(name));
+		let mut delay = RETRY_DELAY;
+
+		for attempt in 1.. {
+			let mut request = Request::builder().method(method).uri(&url);
+
+			if let Some(authorization) = &self.authorization {
+				request = request.header("authorization", authorization);
+			}
+
+			for (name, value) in headers {
+				request = request.header(*name, value);
+			}
+
+			let request = request.body(body.to_owned()).map_err(io::Error::other)?;
+
+			// Cloud drives commonly limit the rate of requests, so those refused as such are retried after a while, as are those which never reached the server.
+
+			let error = match self.agent.run(request) {
+				Ok(response) => return Ok(response),
+				Err(ureq::Error::StatusCode(404)) => return Err(io::Error::new(io::ErrorKind::NotFound, "no such file")),
+				Err(e @ ureq::Error::StatusCode(429 | 502 | 503 | 504)) => e,
+				Err(e @ (ureq::Error::Io(_) | ureq::Error::ConnectionFailed | ureq::Error::HostNotFound | ureq::Error::Timeout(_))) => e,
+				Err(e) => return Err(e.into_io()),
+			};
+
+			if attempt == ATTEMPTS {
+				return Err(error.into_io());
+			}
+
+			thread::sleep(delay);
+			delay *= 2;
+		}
+
+		unreachable!()
+	}
+}
+
+impl Backend for WebDav {
+	fn location(&self) -> &str {
+		&self.location
+	}
+
+	fn list(&self) -> io::Result<Vec<Entry>> {
+		let response = Regex::new(r"(?s)<(?:[\w-]+:)?response\b.*?</(?:[\w-]+:)?response>").unwrap();
+		let href = Regex::new(r"(?s)<(?:[\w-]+:)?href\b[^>]*>(.*?)</").unwrap();
+		let collection = Regex::new(r"<(?:[\w-]+:)?collection\b").unwrap();
+		let md5 = Regex::new(r"(?i)\bMD5:([0-9a-f]{32})\b").unwrap();
+
+		let headers = [("depth", "1".to_owned()), ("content-type", "application/xml; charset=utf-8".to_owned())];
+		let body = self.request("PROPFIND", "", &headers, PROPERTIES)?.into_body().read_to_string().map_err(|e| e.into_io())?;
+
+		// The directory itself is listed alongside its contents, but is skipped as a collection along with any folders.
+
+		Ok(response
+			.find_iter(&body)
+			.map(|r| r.as_str())
+			.filter(|r| !collection.is_match(r))
+			.filter_map(|r| {
+				let href = unescape(href.captures(r)?.get(1)?.as_str().trim());
+				let name = decode(href.trim_end_matches('/').rsplit('/').next()?)?;
+
+				Some(Entry {
+					name,
+					checksum: md5.captures(r).map(|m| m[1].to_ascii_lowercase()),
+				})
+			})
+			.filter(|e| !e.name.is_empty())
+			.collect())
+	}
+
+	fn checksum(&self, entry: &Entry) -> io::Result<String> {
+		let mut response = self.request("GET", &entry.name, &[], "")?;
+		let mut reader = response.body_mut().as_reader();
+		let mut context = md5::Context::new();
+		let mut buffer = [0u8; 64 * 1024];
+
+		loop {
+			let read = reader.read(&mut buffer)?;
+
+			if read == 0 {
+				break;
+			}
+
+			context.consume(&buffer[..read]);
+		}
+
+		Ok(format!("{:x}", context.compute()))
+	}
+
+	fn exists(&self, name: &str) -> io::Result<bool> {
+		match self.request("HEAD", name, &[], "") {
+			Ok(_) => Ok(true),
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+			Err(e) => Err(e),
+		}
+	}
+
+	fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+		let headers = [("destination", format!("{}{}", self.url, encode(to))), ("overwrite", "F".to_owned())];
+
+		self.request("MOVE", from, &headers, "").map(|_| ())
+	}
+
+	fn remove(&self, name: &str) -> io::Result<()> {
+		self.request("DELETE", name, &[], "").map(|_| ())
+	}
+}
+
+/// Percent-encodes the specified path segment for use within a URL.
+fn encode(segment: &str) -> String {
+	let mut encoded = String::with_capacity(segment.len());
+
+	for byte in segment.bytes() {
+		match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(char::from(byte)),
+			_ => {
+				let _ = write!(encoded, "%{:02X}", byte);
+			}
+		}
+	}
+
+	encoded
+}
+
+/// Reverses the percent-encoding of the specified path segment, or nothing should it not be valid UTF-8.
+fn decode(segment: &str) -> Option<String> {
+	let bytes = segment.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+
+	while i < bytes.len() {
+		match (bytes[i], segment.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok())) {
+			(b'%', Some(byte)) => {
+				decoded.push(byte);
+				i += 3;
+			}
+			(byte, _) => {
+				decoded.push(byte);
+				i += 1;
+			}
+		}
+	}
+
+	String::from_utf8(decoded).ok()
+}
+
+/// Reverses the escaping of the predefined entities within the specified XML text.
+fn unescape(text: &str) -> String {
+	text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&#34;", "\"").replace("&amp;", "&")
+}
+
+/// Encodes the specified bytes as (padded) Base64.
+fn base64(bytes: &[u8]) -> String {
+	let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+	for chunk in bytes.chunks(3) {
+		let group = chunk.iter().enumerate().fold(0u32, |group, (i, b)| group | u32::from(*b) << (16 - 8 * i));
+
+		for i in 0..4 {
+			match i <= chunk.len() {
+				true => encoded.push(char::from(BASE64[(group >> (18 - 6 * i) & 0x3f) as usize])),
+				false => encoded.push('='),
+			}
+		}
+	}
+
+	encoded
+}